@@ -41,7 +41,7 @@ fn main() -> ! {
         Ets,
     );
     let mut epd = IT8951::new(display_interface, Config::default())
-        .init(1605)
+        .init(vcom::Vcom::from_volts(-1.605).unwrap())
         .unwrap();
     epd.reset().unwrap();
 
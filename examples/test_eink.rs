@@ -1,4 +1,4 @@
-use it8951::Config;
+use it8951::{vcom::Vcom, Config};
 use linux_embedded_hal::gpio_cdev::{Chip, LineRequestFlags};
 use linux_embedded_hal::spidev::{SpiModeFlags, SpidevOptions};
 use linux_embedded_hal::{CdevPin, Delay, SpidevDevice};
@@ -36,7 +36,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let driver = it8951::interface::IT8951SPIInterface::new(spi, busy, rst, Delay);
     let mut epd = it8951::IT8951::new(driver, Config::default())
-        .init(1670)
+        .init(Vcom::from_volts(-1.67).unwrap())
         .unwrap();
 
     println!(
@@ -0,0 +1,141 @@
+//! Alignment of display and load areas to the controller constraints
+
+use embedded_graphics_core::{pixelcolor::Gray4, prelude::*, primitives::Rectangle};
+
+use crate::{interface, origin::Origin, AreaImgInfo, Error, Run, WaveformMode, IT8951};
+
+/// Pixel alignment of the x position and width of A2 updates
+const A2_ALIGNMENT: u16 = 32;
+
+/// Pixel alignment of the x position and width of packed 4bpp loads
+const LOAD_ALIGNMENT: u16 = 4;
+
+/// Widens x and width of a panel area to multiples of alignment, limited to the panel width
+fn align_x(area: &AreaImgInfo, alignment: u16, panel_width: u16) -> AreaImgInfo {
+    let x = area.area_x - area.area_x % alignment;
+    let end = (area.area_x + area.area_w)
+        .next_multiple_of(alignment)
+        .min(panel_width);
+    AreaImgInfo {
+        area_x: x,
+        area_w: end.saturating_sub(x),
+        ..*area
+    }
+}
+
+impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
+    IT8951<IT8951Interface, TOrigin, Run>
+{
+    /// Widens a panel area to the alignment required by the waveform
+    pub(crate) fn align_display_area(
+        &self,
+        area: AreaImgInfo,
+        mode: WaveformMode,
+    ) -> Result<AreaImgInfo, Error> {
+        Ok(match mode {
+            WaveformMode::A2 => {
+                let aligned = align_x(&area, A2_ALIGNMENT, self.panel_width()?);
+
+                #[cfg(feature = "defmt")]
+                if aligned != area {
+                    defmt::trace!("Aligned A2 area {} to {}", area, aligned);
+                }
+
+                aligned
+            }
+            _ => area,
+        })
+    }
+
+    /// Writes the colors of a area to the image buffer at memory_address
+    /// The area is widened to 4 pixel boundaries in panel coordinates,
+    /// the added pixels are read back from the image buffer and written unchanged
+    pub(crate) fn load_aligned<I>(
+        &mut self,
+        memory_address: u32,
+        area: &Rectangle,
+        colors: I,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Gray4>,
+    {
+        let visible = area.intersection(&self.bounding_box());
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+
+        let physical = self.panel_area_info(&AreaImgInfo::try_from(&visible)?)?;
+        let aligned =
+            self.display_area_info(&align_x(&physical, LOAD_ALIGNMENT, self.panel_width()?))?;
+        let aligned = Rectangle::from(&aligned);
+
+        let mut pixels = if aligned == visible {
+            vec![Gray4::BLACK; (visible.size.width * visible.size.height) as usize]
+        } else {
+            self.read_area_at(memory_address, &aligned)?
+        };
+
+        let width = aligned.size.width as i32;
+        for (point, color) in area.points().zip(colors) {
+            if aligned.contains(point) {
+                let offset = point - aligned.top_left;
+                pixels[(offset.y * width + offset.x) as usize] = color;
+            }
+        }
+
+        self.write_contiguous_at(memory_address, &aligned, pixels)
+    }
+
+    fn panel_width(&self) -> Result<u16, Error> {
+        Ok(self.panel_size()?.width as u16)
+    }
+
+    /// Inverse of panel_area_info, transforms a panel area to display coordinates
+    fn display_area_info(&self, area: &AreaImgInfo) -> Result<AreaImgInfo, Error> {
+        let area = self
+            .panel_orientation()
+            .area_to_display(&area.into(), self.panel_size()?);
+        AreaImgInfo::try_from(&area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_x() {
+        let area = AreaImgInfo {
+            area_x: 33,
+            area_y: 7,
+            area_w: 10,
+            area_h: 5,
+        };
+        assert_eq!(
+            align_x(&area, 32, 1872),
+            AreaImgInfo {
+                area_x: 32,
+                area_y: 7,
+                area_w: 32,
+                area_h: 5,
+            }
+        );
+
+        // limited to the panel width
+        let area = AreaImgInfo {
+            area_x: 1870,
+            area_y: 0,
+            area_w: 2,
+            area_h: 1,
+        };
+        assert_eq!(
+            align_x(&area, 32, 1872),
+            AreaImgInfo {
+                area_x: 1856,
+                area_y: 0,
+                area_w: 16,
+                area_h: 1,
+            }
+        );
+    }
+}
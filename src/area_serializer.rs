@@ -1,48 +1,69 @@
-use crate::{serialization_helper::get_nibbles_per_row, AreaImgInfo};
-use alloc::vec::Vec;
+use crate::{serialization_helper::get_nibbles_per_row, AreaImgInfo, Error};
 use embedded_graphics_core::{
     pixelcolor::{Gray4, GrayColor},
     primitives::Rectangle,
 };
 
 /// Converts a rectangle with a uniform color to frame buffer segments with area information.
+/// The segments are written to a staging buffer of `buffer_size` bytes
 pub struct AreaSerializer {
     area: Rectangle,
     rows_per_step: usize,
-    buffer: Vec<u8>,
+    entries_per_row: usize,
+    data_entry: u8,
 }
 
 impl AreaSerializer {
-    pub fn new(area: Rectangle, color: Gray4, buffer_size: usize) -> Self {
+    pub fn new(area: Rectangle, color: Gray4, buffer_size: usize) -> Result<Self, Error> {
         let raw_color = color.luma();
         let data_entry = raw_color << 4 | raw_color;
 
-        assert!(
-            buffer_size.is_multiple_of(2),
-            "Buffer size must be aligned to u16"
-        );
+        if !buffer_size.is_multiple_of(2) {
+            return Err(Error::Misaligned);
+        }
         // calculate the buffer size
         let entries_per_row = get_nibbles_per_row(area) as usize * 2; // convert length from u16 to u8
-        let rows_per_step = (buffer_size / entries_per_row).min(area.size.height as usize);
-        assert!(rows_per_step > 0, "Buffer size to small for one row");
-        let buffer = vec![data_entry; entries_per_row * rows_per_step];
+        let rows_per_step = if area.is_zero_sized() {
+            0
+        } else {
+            (buffer_size / entries_per_row).min(area.size.height as usize)
+        };
+        if rows_per_step == 0 && !area.is_zero_sized() {
+            return Err(Error::BufferTooSmall);
+        }
 
-        AreaSerializer {
+        Ok(AreaSerializer {
             area,
             rows_per_step,
-            buffer,
-        }
+            entries_per_row,
+            data_entry,
+        })
     }
 }
 
 pub struct AreaSerializerIterator<'a> {
     area_serializer: &'a AreaSerializer,
+    buffer: &'a [u8],
     row: usize,
 }
 impl<'a> AreaSerializerIterator<'a> {
-    pub fn new(area_serializer: &'a AreaSerializer) -> AreaSerializerIterator<'a> {
+    /// Fills the staging buffer with the color, all segments share the buffer
+    /// A buffer smaller than `buffer_size` yields no segments
+    pub fn new(
+        area_serializer: &'a AreaSerializer,
+        buffer: &'a mut [u8],
+    ) -> AreaSerializerIterator<'a> {
+        let len = area_serializer.entries_per_row * area_serializer.rows_per_step;
+        let buffer = match buffer.get_mut(..len) {
+            Some(buffer) => {
+                buffer.fill(area_serializer.data_entry);
+                buffer
+            }
+            None => &mut [],
+        };
         AreaSerializerIterator {
             area_serializer,
+            buffer,
             row: 0,
         }
     }
@@ -53,7 +74,7 @@ impl<'a> Iterator for AreaSerializerIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let area_height = self.area_serializer.area.size.height;
-        if self.row >= area_height as usize {
+        if self.row >= area_height as usize || self.buffer.is_empty() {
             return None;
         }
 
@@ -68,7 +89,7 @@ impl<'a> Iterator for AreaSerializerIterator<'a> {
                 area_w: self.area_serializer.area.size.width as u16,
                 area_h: (self.row - start_row) as u16,
             },
-            &self.area_serializer.buffer,
+            self.buffer,
         ))
     }
 }
@@ -100,8 +121,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -131,8 +154,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -161,8 +186,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -191,8 +218,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -222,8 +251,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
 
         assert_eq!(
             s.next(),
@@ -254,8 +285,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -282,8 +315,10 @@ mod tests {
             },
         };
         let area_s =
-            AreaSerializer::new(area.intersection(&BOUNDING_BOX_DEFAULT), Gray4::new(0xA), 2);
-        let mut s = AreaSerializerIterator::new(&area_s);
+            AreaSerializer::new(area.intersection(&BOUNDING_BOX_DEFAULT), Gray4::new(0xA), 2)
+                .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -322,8 +357,10 @@ mod tests {
             },
         };
         let area_s =
-            AreaSerializer::new(area.intersection(&BOUNDING_BOX_DEFAULT), Gray4::new(0xA), 4);
-        let mut s = AreaSerializerIterator::new(&area_s);
+            AreaSerializer::new(area.intersection(&BOUNDING_BOX_DEFAULT), Gray4::new(0xA), 4)
+                .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -365,8 +402,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -396,8 +435,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -427,8 +468,10 @@ mod tests {
             area.intersection(&BOUNDING_BOX_DEFAULT),
             Gray4::new(0xA),
             1024,
-        );
-        let mut s = AreaSerializerIterator::new(&area_s);
+        )
+        .unwrap();
+        let mut buffer = [0; 1024];
+        let mut s = AreaSerializerIterator::new(&area_s, &mut buffer);
         assert_eq!(
             s.next(),
             Some((
@@ -443,4 +486,24 @@ mod tests {
         );
         assert_eq!(s.next(), None);
     }
+
+    #[test]
+    fn test_area_serializer_errors() {
+        let area = Rectangle::new(Point::new(0, 0), Size::new(32, 2));
+        assert_eq!(
+            AreaSerializer::new(area, Gray4::new(0xA), 3).err(),
+            Some(Error::Misaligned)
+        );
+        assert_eq!(
+            AreaSerializer::new(area, Gray4::new(0xA), 4).err(),
+            Some(Error::BufferTooSmall)
+        );
+
+        let empty = AreaSerializer::new(Rectangle::zero(), Gray4::new(0xA), 4).unwrap();
+        let mut buffer = [0; 4];
+        assert_eq!(
+            AreaSerializerIterator::new(&empty, &mut buffer).next(),
+            None
+        );
+    }
 }
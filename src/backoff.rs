@@ -0,0 +1,93 @@
+//! Exponential backoff for polling the controller
+
+use core::time::Duration;
+
+/*
+   Exponential backoff eventually switches to longer delay.
+   When ussed with FreeRtos Delay, longer delay allows for other tasks to
+   execute instead of busy-loop for longer screen operations
+*/
+const BACKOFF_START_US: u32 = 200;
+
+// Cap max backoff so we won't overshoot timeout significantly
+// Set approximately to free-rtos tick to allow for other tasks to run
+const BACKOFF_CAP_US: u32 = 1000;
+
+/// Delays between polls, starting at 200µs and doubling up to 1ms
+/// The elapsed time is measured with the clock if available,
+/// otherwise it is estimated by summing up the delays
+pub(crate) struct Backoff {
+    delay_us: u32,
+    timeout: Duration,
+    accumulated: Duration,
+    clock: Option<(fn() -> Duration, Duration)>,
+}
+
+impl Backoff {
+    pub(crate) fn new(timeout: Duration, clock: Option<fn() -> Duration>) -> Self {
+        Backoff {
+            delay_us: BACKOFF_START_US,
+            timeout,
+            accumulated: Duration::ZERO,
+            clock: clock.map(|clock| (clock, clock())),
+        }
+    }
+
+    /// Time since the backoff was created
+    pub(crate) fn elapsed(&self) -> Duration {
+        match self.clock {
+            Some((clock, start)) => clock().saturating_sub(start),
+            None => self.accumulated,
+        }
+    }
+
+    /// Returns the next delay or `None` if the timeout elapsed
+    pub(crate) fn next_delay(&mut self) -> Option<Duration> {
+        if self.elapsed() > self.timeout {
+            return None;
+        }
+
+        let delay = Duration::from_micros(self.delay_us as u64);
+        self.accumulated += delay;
+        if self.delay_us < BACKOFF_CAP_US {
+            self.delay_us = (self.delay_us * 2).min(BACKOFF_CAP_US);
+        }
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_cap() {
+        let mut backoff = Backoff::new(Duration::from_millis(10), None);
+        let delays: alloc::vec::Vec<u128> = (0..5)
+            .map(|_| backoff.next_delay().unwrap().as_micros())
+            .collect();
+        assert_eq!(delays, [200, 400, 800, 1000, 1000]);
+    }
+
+    #[test]
+    fn test_backoff_times_out_from_delays() {
+        let mut backoff = Backoff::new(Duration::from_millis(3), None);
+        // 200 + 400 + 800 + 1000 + 1000 = 3400µs
+        assert_eq!((0..10).map_while(|_| backoff.next_delay()).count(), 5);
+        assert!(backoff.next_delay().is_none());
+    }
+
+    #[test]
+    fn test_backoff_times_out_from_clock() {
+        fn clock() -> Duration {
+            // every poll takes 1s
+            static TICKS: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+            Duration::from_secs(TICKS.fetch_add(1, core::sync::atomic::Ordering::Relaxed))
+        }
+
+        let mut backoff = Backoff::new(Duration::from_millis(2500), Some(clock));
+        assert!(backoff.next_delay().is_some());
+        assert!(backoff.next_delay().is_some());
+        assert!(backoff.next_delay().is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! Copy image data between image buffers inside the controller memory
+
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+
+use crate::{
+    image_buffer::ImageBuffer,
+    interface,
+    origin::Origin,
+    serialization_helper::{get_packed_pixel, set_packed_pixel},
+    Error, Run, IT8951,
+};
+
+/// Part of a buffer row, extended to complete u16 words
+struct RowSegment {
+    /// address of the first word
+    addr: u32,
+    /// index of the first pixel inside the segment
+    first_pixel: usize,
+    /// length in bytes
+    len: usize,
+    /// first or last word contains pixels outside of the segment
+    partial: bool,
+}
+
+impl RowSegment {
+    fn new(buffer: &ImageBuffer, x: usize, y: usize, pixels: usize) -> Result<Self, Error> {
+        let bpp = buffer.bpp as usize;
+        let bit_start = x * bpp;
+        let bit_end = (x + pixels) * bpp;
+        let byte_start = bit_start / 16 * 2;
+        let byte_end = bit_end.div_ceil(16) * 2;
+
+        let addr = (y as u32)
+            .checked_mul(buffer.bytes_per_row())
+            .and_then(|offset| offset.checked_add(byte_start as u32))
+            .and_then(|offset| buffer.addr.checked_add(offset))
+            .ok_or(Error::OutOfMemory)?;
+        Ok(RowSegment {
+            addr,
+            first_pixel: (bit_start - byte_start * 8) / bpp,
+            len: byte_end - byte_start,
+            partial: !bit_start.is_multiple_of(16) || !bit_end.is_multiple_of(16),
+        })
+    }
+}
+
+/// Offset between two points, fails with `Error::InvalidArea` on overflow
+fn checked_offset(to: Point, from: Point) -> Result<Point, Error> {
+    Ok(Point::new(
+        to.x.checked_sub(from.x).ok_or(Error::InvalidArea)?,
+        to.y.checked_sub(from.y).ok_or(Error::InvalidArea)?,
+    ))
+}
+
+fn buffer_rect(buffer: &ImageBuffer) -> Rectangle {
+    Rectangle::new(
+        Point::zero(),
+        Size::new(buffer.width as u32, buffer.height as u32),
+    )
+}
+
+impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
+    IT8951<IT8951Interface, TOrigin, Run>
+{
+    /// Copies a area of a image buffer to dst_point in another or the same image buffer
+    /// The data never leaves the controller, only the staging buffers are transferred
+    /// Coordinates are buffer coordinates, the display rotation is not applied
+    /// The copied area is clipped to both buffers, which must be valid and use the same bits per pixel
+    /// The driver's staging buffer is split into a source and a destination half
+    pub fn copy_area(
+        &mut self,
+        src_buffer: &ImageBuffer,
+        src_rect: &Rectangle,
+        dst_buffer: &ImageBuffer,
+        dst_point: Point,
+    ) -> Result<(), Error> {
+        if !src_buffer.is_valid() || !dst_buffer.is_valid() || src_buffer.bpp != dst_buffer.bpp {
+            return Err(Error::InvalidImageBuffer);
+        }
+        // all copied rows are inside of the buffers
+        src_buffer.end_addr()?;
+        dst_buffer.end_addr()?;
+        let bpp = src_buffer.bpp;
+
+        // clip to source and destination buffer
+        let offset = checked_offset(dst_point, src_rect.top_left)?;
+        let src_rect = src_rect.intersection(&buffer_rect(src_buffer));
+        if src_rect.is_zero_sized() {
+            return Ok(());
+        }
+        let dst_rect = Rectangle::new(
+            src_rect
+                .top_left
+                .x
+                .checked_add(offset.x)
+                .zip(src_rect.top_left.y.checked_add(offset.y))
+                .map(|(x, y)| Point::new(x, y))
+                .ok_or(Error::InvalidArea)?,
+            src_rect.size,
+        )
+        .intersection(&buffer_rect(dst_buffer));
+        if dst_rect.is_zero_sized() {
+            return Ok(());
+        }
+        let src_rect = Rectangle::new(checked_offset(dst_rect.top_left, offset)?, dst_rect.size);
+
+        // copy in a order which never overwrites source pixels before they are read
+        let same_buffer = src_buffer.addr == dst_buffer.addr;
+        let bottom_up = same_buffer && offset.y > 0;
+        let right_to_left = same_buffer && offset.x > 0;
+
+        // source and destination get half of the staging buffer each
+        let segment_bytes = (self.config.max_buffer_size / 2) & !1;
+        let chunk_pixels = ((segment_bytes * 8).saturating_sub(15) / bpp as usize).max(1);
+        let width = src_rect.size.width as usize;
+        let height = src_rect.size.height as usize;
+        let chunks = width.div_ceil(chunk_pixels);
+
+        // a unaligned segment may start up to 15 bits into its first word
+        let segment_len = (chunk_pixels * bpp as usize + 15).div_ceil(16) * 2;
+        if segment_len > segment_bytes {
+            return Err(Error::BufferTooSmall);
+        }
+
+        self.with_staging(|it8951, staging| {
+            let (src_data, dst_data) = staging.split_at_mut(segment_bytes);
+            for row in 0..height {
+                let row = if bottom_up { height - 1 - row } else { row };
+                for chunk in 0..chunks {
+                    let chunk = if right_to_left {
+                        chunks - 1 - chunk
+                    } else {
+                        chunk
+                    };
+                    let start = chunk * chunk_pixels;
+                    let pixels = chunk_pixels.min(width - start);
+
+                    let src = RowSegment::new(
+                        src_buffer,
+                        src_rect.top_left.x as usize + start,
+                        src_rect.top_left.y as usize + row,
+                        pixels,
+                    )?;
+                    let dst = RowSegment::new(
+                        dst_buffer,
+                        dst_rect.top_left.x as usize + start,
+                        dst_rect.top_left.y as usize + row,
+                        pixels,
+                    )?;
+
+                    it8951.memory_burst_read(src.addr, &mut src_data[..src.len])?;
+                    // keep the neighbouring pixels sharing a word with the copied pixels
+                    if dst.partial {
+                        it8951.memory_burst_read(dst.addr, &mut dst_data[..dst.len])?;
+                    }
+
+                    for i in 0..pixels {
+                        let value = get_packed_pixel(src_data, src.first_pixel + i, bpp);
+                        set_packed_pixel(dst_data, dst.first_pixel + i, bpp, value);
+                    }
+
+                    it8951.memory_burst_write(dst.addr, &mut dst_data[..dst.len])?;
+                }
+            }
+            Ok(())
+        })?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!(
+            "Copied {}x{} pixels from {:x} to {:x}",
+            width,
+            height,
+            src_buffer.addr,
+            dst_buffer.addr
+        );
+
+        Ok(())
+    }
+}
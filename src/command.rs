@@ -18,3 +18,8 @@ pub const USDEF_I80_CMD_DPY_AREA: u16 = 0x0034;
 pub const USDEF_I80_CMD_GET_DEV_INFO: u16 = 0x0302;
 pub const USDEF_I80_CMD_DPY_BUF_AREA: u16 = 0x0037;
 pub const USDEF_I80_CMD_VCOM: u16 = 0x0039;
+pub const USDEF_I80_CMD_TEMP: u16 = 0x0040;
+
+// Arguments of USDEF_I80_CMD_TEMP
+pub const TEMP_GET: u16 = 0x0000;
+pub const TEMP_SET_FORCED: u16 = 0x0001;
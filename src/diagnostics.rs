@@ -0,0 +1,313 @@
+//! Self test of the controller, its memory and the interface
+
+use alloc::vec::Vec;
+use core::time::Duration;
+use embedded_graphics_core::{pixelcolor::Gray4, prelude::*};
+
+use crate::{
+    image_buffer::SDRAM_START, interface, origin::Origin, register, DevInfo, Error, Run,
+    WaveformMode, IT8951,
+};
+
+/// Number of register reads used to measure the response time
+const RESPONSE_SAMPLES: u32 = 8;
+
+/// Values written to the registers, restored afterwards
+const REGISTER_PATTERNS: [u16; 2] = [0xA5A4, 0x5A5A];
+
+/// Registers written by the register check, all 16 bits are implemented
+/// Only the low word of the load image start address is tested,
+/// the high word implements just the address bits of the SDRAM
+const TESTED_REGISTERS: [u16; 1] = [register::LISAR];
+
+/// Number of patterns written to the memory
+const MEMORY_PATTERNS: usize = 4;
+
+/// Problems found in the device info
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DevInfoIssue {
+    /// The panel width or height is zero
+    InvalidPanelSize,
+    /// The default image buffer does not fit into `Config::memory_size`
+    ImageBufferOutsideMemory,
+    /// The firmware version is empty
+    MissingFirmwareVersion,
+    /// The LUT version is empty
+    MissingLutVersion,
+    /// The device info differs from the one read during init
+    ChangedSinceInit,
+}
+
+/// Device info read by the self test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevInfoCheck {
+    /// Device info read from the controller
+    pub dev_info: DevInfo,
+    /// Problems found in the device info, empty if the values are plausible
+    pub issues: Vec<DevInfoIssue>,
+}
+
+/// A byte of the SDRAM which did not return the written value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MemoryMismatch {
+    /// Address of the byte
+    pub address: u32,
+    /// Written value
+    pub written: u8,
+    /// Value read back
+    pub read: u8,
+}
+
+/// Result of writing and reading back test patterns in the SDRAM
+/// The tested memory is allocated after the default image buffer, the displayed image is not touched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MemoryCheck {
+    /// Start address of the tested memory
+    pub address: u32,
+    /// Number of tested bytes
+    pub len: usize,
+    /// Number of written patterns
+    pub patterns: usize,
+    /// Number of bytes which did not return the written pattern, summed over all patterns
+    pub errors: usize,
+    /// First byte which did not return the written pattern
+    pub first_mismatch: Option<MemoryMismatch>,
+}
+
+/// A register which did not return the written value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegisterMismatch {
+    /// Register address
+    pub register: u16,
+    /// Written value
+    pub written: u16,
+    /// Value read back
+    pub read: u16,
+}
+
+/// Time the controller needs to answer a register read, including the wait for HRDY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResponseTime {
+    /// Number of measured register reads
+    pub samples: u32,
+    /// Fastest response
+    pub min: Duration,
+    /// Slowest response
+    pub max: Duration,
+    /// Average response
+    pub mean: Duration,
+}
+
+/// Result of the visible test refresh
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RefreshCheck {
+    /// Waveform used for the refreshes
+    pub mode: WaveformMode,
+    /// Time of the black and white refresh cycle, `None` without `Config::clock`
+    pub duration: Option<Duration>,
+}
+
+/// Report of `IT8951::self_test`
+/// Every check is run independently, a failed transfer is reported as the error of its check
+#[derive(Debug)]
+pub struct SelfTestReport {
+    /// Device info read from the controller
+    pub dev_info: Result<DevInfoCheck, Error>,
+    /// SDRAM pattern test of free memory after the default image buffer
+    pub memory: Result<MemoryCheck, Error>,
+    /// Registers which did not keep the written values
+    pub registers: Result<Vec<RegisterMismatch>, Error>,
+    /// Response times of the controller, `None` without `Config::clock`
+    pub response_time: Result<Option<ResponseTime>, Error>,
+    /// Visible test refresh, `None` if it was not requested
+    pub refresh: Option<Result<RefreshCheck, Error>>,
+}
+
+impl SelfTestReport {
+    /// True if all checks passed
+    pub fn passed(&self) -> bool {
+        self.dev_info.as_ref().is_ok_and(|c| c.issues.is_empty())
+            && self.memory.as_ref().is_ok_and(|c| c.errors == 0)
+            && self.registers.as_ref().is_ok_and(|m| m.is_empty())
+            && self.response_time.is_ok()
+            && self.refresh.as_ref().is_none_or(|r| r.is_ok())
+    }
+}
+
+impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
+    IT8951<IT8951Interface, TOrigin, Run>
+{
+    /// Checks the controller, its memory and the interface and reports all findings
+    /// The device info is read and checked, test patterns are written to the SDRAM,
+    /// register writes are read back and the response time of the controller is measured
+    /// If a waveform is given, the display is refreshed black and white with it,
+    /// which clears the image buffer. Otherwise the displayed content is not changed
+    pub fn self_test(&mut self, refresh: Option<WaveformMode>) -> SelfTestReport {
+        let report = SelfTestReport {
+            dev_info: self.check_dev_info(),
+            memory: self.check_memory(),
+            registers: self.check_registers(),
+            response_time: self.measure_response_time(),
+            refresh: refresh.map(|mode| self.check_refresh(mode)),
+        };
+
+        #[cfg(feature = "defmt")]
+        defmt::info!("Self test passed: {}", report.passed());
+
+        report
+    }
+
+    fn check_dev_info(&mut self) -> Result<DevInfoCheck, Error> {
+        let dev_info = self.get_system_info()?;
+
+        let mut issues = Vec::new();
+        if dev_info.panel_width == 0 || dev_info.panel_height == 0 {
+            issues.push(DevInfoIssue::InvalidPanelSize);
+        }
+        let frame_end = dev_info.memory_address as u64
+            + dev_info.panel_width as u64 * dev_info.panel_height as u64;
+        if frame_end > SDRAM_START as u64 + self.config.memory_size as u64 {
+            issues.push(DevInfoIssue::ImageBufferOutsideMemory);
+        }
+        if dev_info.firmware_version.is_empty() {
+            issues.push(DevInfoIssue::MissingFirmwareVersion);
+        }
+        if dev_info.lut_version.is_empty() {
+            issues.push(DevInfoIssue::MissingLutVersion);
+        }
+        if self.dev_info.as_ref().is_some_and(|info| *info != dev_info) {
+            issues.push(DevInfoIssue::ChangedSinceInit);
+        }
+
+        Ok(DevInfoCheck { dev_info, issues })
+    }
+
+    fn check_memory(&mut self) -> Result<MemoryCheck, Error> {
+        // a single 8bpp row of at most the staging buffer size
+        let len = self.config.max_buffer_size.min(u16::MAX as usize) & !1;
+        let buffer = self.allocator()?.allocate(len as u16, 1, 8)?;
+        let address = buffer.addr;
+
+        let mut check = MemoryCheck {
+            address,
+            len,
+            patterns: MEMORY_PATTERNS,
+            errors: 0,
+            first_mismatch: None,
+        };
+        let result = self.with_staging(|it8951, staging| {
+            let data = &mut staging[..len];
+            for pattern in 0..MEMORY_PATTERNS {
+                // the write swaps the bytes in place
+                data.iter_mut()
+                    .enumerate()
+                    .for_each(|(offset, byte)| *byte = memory_pattern(pattern, offset));
+                it8951.memory_burst_write(address, data)?;
+                it8951.memory_burst_read(address, data)?;
+
+                for (offset, read) in data.iter().enumerate() {
+                    let written = memory_pattern(pattern, offset);
+                    if *read != written {
+                        check.errors += 1;
+                        check.first_mismatch.get_or_insert(MemoryMismatch {
+                            address: address + offset as u32,
+                            written,
+                            read: *read,
+                        });
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        // the buffer is released even if a pattern transfer failed
+        self.allocator()?.free(buffer)?;
+        result?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Memory check {}", check);
+
+        Ok(check)
+    }
+
+    fn check_registers(&mut self) -> Result<Vec<RegisterMismatch>, Error> {
+        let mut mismatches = Vec::new();
+        for register in TESTED_REGISTERS {
+            let saved = self.read_register(register)?;
+            for written in REGISTER_PATTERNS {
+                self.write_register(register, written)?;
+                let read = self.read_register(register)?;
+                if read != written {
+                    mismatches.push(RegisterMismatch {
+                        register,
+                        written,
+                        read,
+                    });
+                }
+            }
+            self.write_register(register, saved)?;
+        }
+        Ok(mismatches)
+    }
+
+    fn measure_response_time(&mut self) -> Result<Option<ResponseTime>, Error> {
+        let Some(clock) = self.config.clock else {
+            return Ok(None);
+        };
+
+        let mut min = Duration::MAX;
+        let mut max = Duration::ZERO;
+        let mut total = Duration::ZERO;
+        for _ in 0..RESPONSE_SAMPLES {
+            let start = clock();
+            self.read_register(register::LUTAFSR)?;
+            let elapsed = clock().saturating_sub(start);
+            min = min.min(elapsed);
+            max = max.max(elapsed);
+            total += elapsed;
+        }
+
+        Ok(Some(ResponseTime {
+            samples: RESPONSE_SAMPLES,
+            min,
+            max,
+            mean: total / RESPONSE_SAMPLES,
+        }))
+    }
+
+    fn check_refresh(&mut self, mode: WaveformMode) -> Result<RefreshCheck, Error> {
+        let start = self.config.clock.map(|clock| clock());
+        for color in [Gray4::BLACK, Gray4::WHITE] {
+            self.clear(color)?;
+            self.display(mode)?;
+            self.wait_for_display_ready()?;
+        }
+        self.clear_dirty();
+
+        Ok(RefreshCheck {
+            mode,
+            duration: self
+                .config
+                .clock
+                .zip(start)
+                .map(|(clock, start)| clock().saturating_sub(start)),
+        })
+    }
+}
+
+/// Byte of a test pattern at offset
+fn memory_pattern(pattern: usize, offset: usize) -> u8 {
+    match pattern {
+        0 => 0x00,
+        1 => 0xFF,
+        2 if offset.is_multiple_of(2) => 0xAA,
+        2 => 0x55,
+        _ => (offset ^ (offset >> 8)) as u8,
+    }
+}
@@ -0,0 +1,238 @@
+//! Tracking of drawn areas which need a display refresh
+
+use alloc::vec::Vec;
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+
+use crate::waveform_policy::AreaContent;
+
+/// Smallest rectangle containing both rectangles
+pub(crate) fn union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let top_left = a.top_left.component_min(b.top_left);
+    let bottom_right = (a.top_left + a.size).component_max(b.top_left + b.size);
+    Rectangle::with_corners(top_left, bottom_right - Point::new(1, 1))
+}
+
+fn area(rect: &Rectangle) -> u64 {
+    rect.size.width as u64 * rect.size.height as u64
+}
+
+/// Number of pixels in the union of both rectangles, which are part of neither rectangle
+fn overdraw(a: &Rectangle, b: &Rectangle) -> u64 {
+    let covered = area(a) + area(b) - area(&a.intersection(b));
+    area(&union(a, b)) - covered
+}
+
+/// Percentage of pixels in the union of both rectangles, which are part of neither rectangle
+fn overdraw_percent(a: &Rectangle, b: &Rectangle) -> u64 {
+    overdraw(a, b) * 100 / area(&union(a, b))
+}
+
+/// Area which needs a display refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRegion {
+    /// Area in display coordinates
+    pub area: Rectangle,
+    /// Content drawn into the area
+    pub content: AreaContent,
+}
+
+impl DirtyRegion {
+    fn merge(&self, other: &DirtyRegion) -> DirtyRegion {
+        DirtyRegion {
+            area: union(&self.area, &other.area),
+            content: AreaContent {
+                levels: self.content.levels | other.content.levels,
+            },
+        }
+    }
+}
+
+/// Bounded set of dirty regions
+/// Overlapping regions and regions with a small overdraw are merged
+/// If the set is full, the two regions with the least overdraw are merged
+#[derive(Debug)]
+pub struct DirtyRegions {
+    regions: Vec<DirtyRegion>,
+    max_regions: usize,
+    overdraw_percent: u8,
+}
+
+impl DirtyRegions {
+    /// Creates a empty set
+    /// max_regions: max number of tracked regions, at least one region is tracked
+    /// overdraw_percent: regions are merged, if at most this percentage of the merged region was not dirty
+    pub fn new(max_regions: usize, overdraw_percent: u8) -> Self {
+        DirtyRegions {
+            regions: Vec::new(),
+            max_regions: max_regions.max(1),
+            overdraw_percent,
+        }
+    }
+
+    /// Marks a area as dirty
+    pub fn add(&mut self, area: Rectangle, content: AreaContent) {
+        self.add_region(DirtyRegion { area, content });
+    }
+
+    pub(crate) fn add_region(&mut self, region: DirtyRegion) {
+        if region.area.is_zero_sized() {
+            return;
+        }
+
+        // merge with all regions that overlap or are close enough
+        let mut region = region;
+        while let Some(pos) = self.regions.iter().position(|other| {
+            !other.area.intersection(&region.area).is_zero_sized()
+                || overdraw_percent(&other.area, &region.area) <= self.overdraw_percent as u64
+        }) {
+            region = self.regions.swap_remove(pos).merge(&region);
+        }
+        self.regions.push(region);
+
+        while self.regions.len() > self.max_regions {
+            self.merge_closest_pair();
+        }
+    }
+
+    fn merge_closest_pair(&mut self) {
+        let mut best = (0, 1, u64::MAX);
+        for i in 0..self.regions.len() {
+            for j in i + 1..self.regions.len() {
+                let overdraw = overdraw(&self.regions[i].area, &self.regions[j].area);
+                if overdraw < best.2 {
+                    best = (i, j, overdraw);
+                }
+            }
+        }
+
+        // the merged region may overlap other regions
+        let (i, j, _) = best;
+        let b = self.regions.swap_remove(j);
+        let a = self.regions.swap_remove(i);
+        self.add_region(a.merge(&b));
+    }
+
+    /// The current dirty regions
+    pub fn regions(&self) -> &[DirtyRegion] {
+        &self.regions
+    }
+
+    /// True if no area is dirty
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Removes all dirty regions
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// Removes and returns a dirty region
+    pub(crate) fn pop(&mut self) -> Option<DirtyRegion> {
+        self.regions.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union() {
+        let a = Rectangle::new(Point::new(1, 2), Size::new(3, 4));
+        let b = Rectangle::new(Point::new(5, 0), Size::new(1, 1));
+        assert_eq!(
+            union(&a, &b),
+            Rectangle::new(Point::new(1, 0), Size::new(5, 6))
+        );
+    }
+
+    #[test]
+    fn test_overlapping_regions_are_merged() {
+        let mut dirty = DirtyRegions::new(8, 0);
+        dirty.add(
+            Rectangle::new(Point::new(0, 0), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        dirty.add(
+            Rectangle::new(Point::new(5, 5), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        assert_eq!(
+            dirty.regions(),
+            &[DirtyRegion {
+                area: Rectangle::new(Point::new(0, 0), Size::new(15, 15)),
+                content: AreaContent::UNKNOWN,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_distant_regions_are_kept() {
+        let mut dirty = DirtyRegions::new(8, 25);
+        dirty.add(
+            Rectangle::new(Point::new(0, 0), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        dirty.add(
+            Rectangle::new(Point::new(100, 100), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        assert_eq!(dirty.regions().len(), 2);
+
+        // adjacent regions have no overdraw
+        dirty.add(
+            Rectangle::new(Point::new(10, 0), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        assert_eq!(dirty.regions().len(), 2);
+        assert!(dirty
+            .regions()
+            .iter()
+            .any(|r| r.area == Rectangle::new(Point::new(0, 0), Size::new(20, 10))));
+    }
+
+    #[test]
+    fn test_regions_are_bounded() {
+        let mut dirty = DirtyRegions::new(2, 0);
+        dirty.add(
+            Rectangle::new(Point::new(0, 0), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        dirty.add(
+            Rectangle::new(Point::new(100, 100), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        dirty.add(
+            Rectangle::new(Point::new(0, 20), Size::new(10, 10)),
+            AreaContent::UNKNOWN,
+        );
+        assert_eq!(dirty.regions().len(), 2);
+        assert!(dirty
+            .regions()
+            .iter()
+            .any(|r| r.area == Rectangle::new(Point::new(0, 0), Size::new(10, 30))));
+
+        dirty.clear();
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn test_merged_content() {
+        let mut dirty = DirtyRegions::new(8, 0);
+        dirty.add(
+            Rectangle::new(Point::new(0, 0), Size::new(10, 10)),
+            AreaContent { levels: 1 << 0 },
+        );
+        dirty.add(
+            Rectangle::new(Point::new(5, 5), Size::new(10, 10)),
+            AreaContent { levels: 1 << 15 },
+        );
+        assert_eq!(
+            dirty.regions()[0].content,
+            AreaContent {
+                levels: 1 << 0 | 1 << 15
+            }
+        );
+    }
+}
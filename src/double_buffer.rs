@@ -0,0 +1,199 @@
+//! Double buffering with page flipping
+
+use embedded_graphics_core::{pixelcolor::Gray4, prelude::*, primitives::Rectangle};
+
+use crate::{
+    dirty::union, image_buffer::ImageBuffer, interface::IT8951Interface, origin::Origin,
+    AreaImgInfo, Error, Rotation, Run, WaveformMode, IT8951,
+};
+
+/// Driver in double buffering mode
+/// All drawing operations target the back buffer, while the panel shows the front buffer
+/// `flip` displays the back buffer and swaps both buffers
+pub struct DoubleBuffered<Interface, TOrigin: Origin> {
+    driver: IT8951<Interface, TOrigin, Run>,
+    buffers: [ImageBuffer; 2],
+    back: usize,
+    copy_forward: bool,
+    // area drawn into the back buffer since the last flip
+    dirty: Option<Rectangle>,
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> IT8951<Interface, TOrigin, Run> {
+    /// Switch to double buffering
+    /// The default image buffer becomes the front buffer, a second buffer is allocated as back buffer
+    /// With copy_forward the back buffer always contains the complete frame:
+    /// the front buffer is copied initially and the drawn content is copied forward on every flip
+    pub fn into_double_buffered(
+        mut self,
+        copy_forward: bool,
+    ) -> Result<DoubleBuffered<Interface, TOrigin>, Error> {
+        let front = self.image_buffer()?;
+        let back = self.alloc_image_buffer(front.width, front.height, front.bpp)?;
+
+        if copy_forward {
+            let frame = Rectangle::new(
+                Point::zero(),
+                Size::new(front.width as u32, front.height as u32),
+            );
+            self.copy_area(&front, &frame, &back, Point::zero())?;
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Double buffering with back buffer {}", back);
+
+        Ok(DoubleBuffered {
+            driver: self,
+            buffers: [front, back],
+            back: 1,
+            copy_forward,
+            dirty: None,
+        })
+    }
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> DoubleBuffered<Interface, TOrigin> {
+    /// Access the driver, e.g. for power management or partial updates of the front buffer
+    pub fn driver(&mut self) -> &mut IT8951<Interface, TOrigin, Run> {
+        &mut self.driver
+    }
+
+    /// Changes the display rotation, see `IT8951::set_rotation`
+    /// Both buffers keep their content and must be redrawn
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.driver.set_rotation(rotation);
+        self.dirty = None;
+    }
+
+    /// The buffer all drawing operations target
+    pub fn back_buffer(&self) -> ImageBuffer {
+        self.buffers[self.back]
+    }
+
+    /// The buffer currently shown on the panel
+    pub fn front_buffer(&self) -> ImageBuffer {
+        self.buffers[self.back ^ 1]
+    }
+
+    /// Display the back buffer and swap front and back buffer
+    pub fn flip(&mut self, mode: WaveformMode) -> Result<(), Error> {
+        let size = self.driver.size();
+        let back = self.back_buffer();
+        self.driver.display_area_buf(
+            &AreaImgInfo {
+                area_x: 0,
+                area_y: 0,
+                area_w: size.width as u16,
+                area_h: size.height as u16,
+            },
+            mode,
+            back.addr,
+        )?;
+        self.back ^= 1;
+
+        if let Some(dirty) = self.dirty.take() {
+            if self.copy_forward {
+                // drawing uses display coordinates, the buffers are not rotated
+                let area = self
+                    .driver
+                    .panel_area_info(&AreaImgInfo::try_from(&dirty)?)?;
+                let area = Rectangle::from(&area);
+                let (front, back) = (self.front_buffer(), self.back_buffer());
+                self.driver.copy_area(&front, &area, &back, area.top_left)?;
+            }
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Flipped to buffer {}", back);
+
+        Ok(())
+    }
+
+    /// Leave double buffering
+    /// The buffer shown on the panel becomes the default image buffer again
+    pub fn into_inner(mut self) -> Result<IT8951<Interface, TOrigin, Run>, Error> {
+        let front = self.front_buffer();
+        let back = self.back_buffer();
+        if self.back == 0 {
+            // the allocated buffer is shown, copy it back into the default image buffer
+            let frame = Rectangle::new(
+                Point::zero(),
+                Size::new(front.width as u32, front.height as u32),
+            );
+            self.driver
+                .copy_area(&front, &frame, &back, Point::zero())?;
+            self.driver.free_image_buffer(front)?;
+        } else {
+            self.driver.free_image_buffer(back)?;
+        }
+        Ok(self.driver)
+    }
+
+    fn mark_dirty(&mut self, area: &Rectangle) {
+        let area = area.intersection(&self.driver.bounding_box());
+        if area.is_zero_sized() {
+            return;
+        }
+        self.dirty = Some(match &self.dirty {
+            Some(dirty) => union(dirty, &area),
+            None => area,
+        });
+    }
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> DrawTarget
+    for DoubleBuffered<Interface, TOrigin>
+{
+    type Color = Gray4;
+
+    type Error = Error;
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.mark_dirty(area);
+        let back = self.back_buffer();
+        self.driver.fill_solid_at(back.addr, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.mark_dirty(area);
+        let back = self.back_buffer();
+        self.driver.fill_contiguous_at(back.addr, area, colors)
+    }
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let back = self.back_buffer();
+        let bounding_box = self.driver.bounding_box();
+        let driver = &mut self.driver;
+        let mut dirty = self.dirty;
+        let result = driver.draw_iter_at(
+            back.addr,
+            pixels
+                .into_iter()
+                // pixels outside of the display are not drawn
+                .filter(|Pixel(point, _)| bounding_box.contains(*point))
+                .inspect(|Pixel(point, _)| {
+                    let pixel = Rectangle::new(*point, Size::new(1, 1));
+                    dirty = Some(match &dirty {
+                        Some(dirty) => union(dirty, &pixel),
+                        None => pixel,
+                    });
+                }),
+        );
+        self.dirty = dirty;
+        result
+    }
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> OriginDimensions
+    for DoubleBuffered<Interface, TOrigin>
+{
+    fn size(&self) -> Size {
+        self.driver.size()
+    }
+}
@@ -0,0 +1,356 @@
+//! Ghosting budget for fast display updates
+//!
+//! Fast waveforms (DU, DU4, A2) leave ghosting on the panel.
+//! The panel is divided into tiles, which count the fast updates since their last cleansing refresh.
+//! Once a tile exceeded its budget, the next fast update touching it is promoted to a cleansing refresh.
+
+use alloc::vec::Vec;
+
+use crate::{waveform_table::WaveformTable, AreaImgInfo, Error, WaveformMode};
+
+/// Area refreshed by a cleansing refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CleansingScope {
+    /// Only the area of the promoted update is refreshed
+    Region,
+    /// The whole screen is refreshed
+    Screen,
+}
+
+/// Configuration of the ghosting budget
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GhostingConfig {
+    /// Edge length of the square tiles in panel pixels
+    pub tile_size: u16,
+    /// Number of fast updates per tile before a cleansing refresh is done
+    pub budget: u16,
+    /// Area refreshed by a cleansing refresh
+    pub scope: CleansingScope,
+    /// Waveform of the cleansing refresh
+    /// `WaveformMode::Init` erases the panel to white, the content must be refreshed afterwards
+    pub cleansing_mode: WaveformMode,
+}
+
+impl Default for GhostingConfig {
+    fn default() -> Self {
+        Self {
+            tile_size: 64,
+            budget: 20,
+            scope: CleansingScope::Region,
+            cleansing_mode: WaveformMode::GrayscaleClearing16,
+        }
+    }
+}
+
+/// Counters of fast updates per panel tile
+/// Tiles use panel coordinates and are independent of the display rotation
+#[derive(Debug)]
+pub struct GhostingBudget {
+    config: GhostingConfig,
+    panel_width: u16,
+    panel_height: u16,
+    columns: usize,
+    counters: Vec<u16>,
+}
+
+fn is_fast(mode: WaveformMode) -> bool {
+    matches!(
+        mode,
+        WaveformMode::DirectUpdate | WaveformMode::A2 | WaveformMode::DU4
+    )
+}
+
+fn is_cleansing(mode: WaveformMode) -> bool {
+    matches!(mode, WaveformMode::Init | WaveformMode::GrayscaleClearing16)
+}
+
+impl GhostingBudget {
+    /// Creates counters for a panel, all tiles start clean
+    pub fn new(config: GhostingConfig, panel_width: u16, panel_height: u16) -> Self {
+        let config = GhostingConfig {
+            tile_size: config.tile_size.max(1),
+            ..config
+        };
+        let columns = panel_width.div_ceil(config.tile_size) as usize;
+        let rows = panel_height.div_ceil(config.tile_size) as usize;
+        GhostingBudget {
+            config,
+            panel_width,
+            panel_height,
+            columns,
+            counters: vec![0; columns * rows],
+        }
+    }
+
+    /// The used configuration
+    pub fn config(&self) -> &GhostingConfig {
+        &self.config
+    }
+
+    /// Number of fast updates of the tile at the given panel position since its last cleansing
+    pub fn counter(&self, x: u16, y: u16) -> u16 {
+        let index = (y / self.config.tile_size) as usize * self.columns
+            + (x / self.config.tile_size) as usize;
+        self.counters.get(index).copied().unwrap_or(0)
+    }
+
+    /// Highest counter of all tiles
+    pub fn max_counter(&self) -> u16 {
+        self.counters.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Resets all counters, e.g. after a full refresh done outside of the driver
+    pub fn reset(&mut self) {
+        self.counters.iter_mut().for_each(|c| *c = 0);
+    }
+
+    /// Accounts a update of a panel area
+    /// Returns the area, waveform and mode number to use, which are promoted to a cleansing refresh
+    /// if a touched tile exceeded its budget
+    /// Without screen_allowed the promoted update is limited to its area
+    /// The counters are only changed if the table contains the resulting waveform
+    pub(crate) fn update(
+        &mut self,
+        area: AreaImgInfo,
+        mode: WaveformMode,
+        screen_allowed: bool,
+        table: &WaveformTable,
+    ) -> Result<(AreaImgInfo, WaveformMode, u16), Error> {
+        let fast = is_fast(mode);
+        let exceeded = fast
+            && self
+                .tiles(&area)
+                .any(|index| self.counters[index] >= self.config.budget);
+        let (area, mode) = match self.config.scope {
+            _ if !exceeded => (area, mode),
+            CleansingScope::Screen if screen_allowed => (
+                AreaImgInfo {
+                    area_x: 0,
+                    area_y: 0,
+                    area_w: self.panel_width,
+                    area_h: self.panel_height,
+                },
+                self.config.cleansing_mode,
+            ),
+            _ => (area, self.config.cleansing_mode),
+        };
+        let number = table.number(mode)?;
+
+        if exceeded {
+            #[cfg(feature = "defmt")]
+            defmt::debug!("Ghosting budget exceeded, cleansing area {}", area);
+
+            self.cleanse(&area);
+        } else if fast {
+            for index in self.tiles(&area).collect::<Vec<_>>() {
+                self.counters[index] = self.counters[index].saturating_add(1);
+            }
+        } else if is_cleansing(mode) {
+            self.cleanse(&area);
+        }
+        Ok((area, mode, number))
+    }
+
+    /// Partially covered tiles are counted as cleansed as well
+    fn cleanse(&mut self, area: &AreaImgInfo) {
+        for index in self.tiles(area).collect::<Vec<_>>() {
+            self.counters[index] = 0;
+        }
+    }
+
+    /// Indices of all tiles touched by the area
+    fn tiles(&self, area: &AreaImgInfo) -> impl Iterator<Item = usize> {
+        let tile = self.config.tile_size;
+        let columns = self.columns;
+        let x_end = area
+            .area_x
+            .saturating_add(area.area_w)
+            .min(self.panel_width);
+        let y_end = area
+            .area_y
+            .saturating_add(area.area_h)
+            .min(self.panel_height);
+        let (x0, x1) = (area.area_x / tile, x_end.div_ceil(tile));
+        let (y0, y1) = (area.area_y / tile, y_end.div_ceil(tile));
+        (y0..y1).flat_map(move |y| (x0..x1).map(move |x| y as usize * columns + x as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: u16, y: u16, w: u16, h: u16) -> AreaImgInfo {
+        AreaImgInfo {
+            area_x: x,
+            area_y: y,
+            area_w: w,
+            area_h: h,
+        }
+    }
+
+    #[test]
+    fn test_budget_promotes_region() {
+        let config = GhostingConfig {
+            tile_size: 10,
+            budget: 2,
+            ..Default::default()
+        };
+        let mut budget = GhostingBudget::new(config, 100, 50);
+
+        for _ in 0..2 {
+            let (_, mode, _) = budget
+                .update(
+                    area(5, 5, 10, 10),
+                    WaveformMode::DirectUpdate,
+                    true,
+                    &WaveformTable::STANDARD,
+                )
+                .unwrap();
+            assert_eq!(mode as u16, WaveformMode::DirectUpdate as u16);
+        }
+        assert_eq!(budget.counter(0, 0), 2);
+        assert_eq!(budget.counter(19, 19), 2);
+        assert_eq!(budget.counter(20, 0), 0);
+
+        // untouched tiles are still within budget
+        let (_, mode, _) = budget
+            .update(
+                area(50, 0, 10, 10),
+                WaveformMode::A2,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        assert_eq!(mode as u16, WaveformMode::A2 as u16);
+
+        let (promoted, mode, _) = budget
+            .update(
+                area(15, 15, 2, 2),
+                WaveformMode::DU4,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        assert_eq!(promoted, area(15, 15, 2, 2));
+        assert_eq!(mode as u16, WaveformMode::GrayscaleClearing16 as u16);
+        assert_eq!(budget.counter(10, 10), 0);
+        assert_eq!(budget.counter(0, 0), 2);
+    }
+
+    #[test]
+    fn test_budget_promotes_screen() {
+        let config = GhostingConfig {
+            tile_size: 10,
+            budget: 1,
+            scope: CleansingScope::Screen,
+            cleansing_mode: WaveformMode::Init,
+        };
+        let mut budget = GhostingBudget::new(config, 100, 50);
+
+        budget
+            .update(
+                area(0, 0, 10, 10),
+                WaveformMode::A2,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        budget
+            .update(
+                area(90, 40, 10, 10),
+                WaveformMode::A2,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        let (promoted, mode, _) = budget
+            .update(
+                area(0, 0, 1, 1),
+                WaveformMode::A2,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        assert_eq!(promoted, area(0, 0, 100, 50));
+        assert_eq!(mode as u16, WaveformMode::Init as u16);
+        assert_eq!(budget.max_counter(), 0);
+
+        // e.g. hardware fills must not refresh the whole screen
+        budget
+            .update(
+                area(0, 0, 10, 10),
+                WaveformMode::A2,
+                false,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        let (promoted, _, _) = budget
+            .update(
+                area(0, 0, 1, 1),
+                WaveformMode::A2,
+                false,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        assert_eq!(promoted, area(0, 0, 1, 1));
+    }
+
+    #[test]
+    fn test_cleansing_update_resets_counters() {
+        let mut budget = GhostingBudget::new(GhostingConfig::default(), 1872, 1404);
+        budget
+            .update(
+                area(0, 0, 1872, 1404),
+                WaveformMode::DirectUpdate,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        budget
+            .update(
+                area(0, 0, 64, 64),
+                WaveformMode::GL16,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        assert_eq!(budget.counter(0, 0), 1);
+
+        budget
+            .update(
+                area(0, 0, 64, 64),
+                WaveformMode::GrayscaleClearing16,
+                true,
+                &WaveformTable::STANDARD,
+            )
+            .unwrap();
+        assert_eq!(budget.counter(0, 0), 0);
+        assert_eq!(budget.max_counter(), 1);
+
+        budget.reset();
+        assert_eq!(budget.max_counter(), 0);
+    }
+
+    #[test]
+    fn test_unsupported_cleansing_mode_keeps_counters() {
+        let config = GhostingConfig {
+            tile_size: 10,
+            budget: 1,
+            ..Default::default()
+        };
+        let table = WaveformTable::STANDARD.without(WaveformMode::GrayscaleClearing16);
+        let mut budget = GhostingBudget::new(config, 100, 50);
+
+        budget
+            .update(area(0, 0, 10, 10), WaveformMode::A2, true, &table)
+            .unwrap();
+        assert_eq!(
+            budget.update(area(0, 0, 10, 10), WaveformMode::A2, true, &table),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert_eq!(budget.counter(0, 0), 1);
+    }
+}
@@ -0,0 +1,233 @@
+//! Image buffers in the controller SDRAM
+
+use crate::Error;
+use alloc::vec::Vec;
+
+/// Alignment in bytes of allocated buffers
+const ALIGNMENT: u32 = 4;
+
+/// Address of the first byte of the controller SDRAM
+pub const SDRAM_START: u32 = 0;
+
+/// Describes a image buffer in the controller SDRAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ImageBuffer {
+    /// start address of the buffer in the controller ram
+    pub addr: u32,
+    /// width in pixel
+    pub width: u16,
+    /// height in pixel
+    pub height: u16,
+    /// bits per pixel, one of 1, 2, 4 or 8
+    pub bpp: u8,
+}
+
+impl ImageBuffer {
+    /// Number of bytes of a single row
+    /// Each row is aligned to u16
+    pub fn bytes_per_row(&self) -> u32 {
+        (self.width as u32 * self.bpp as u32).div_ceil(16) * 2
+    }
+
+    /// Number of bytes of the complete buffer
+    pub fn size_bytes(&self) -> u32 {
+        self.bytes_per_row() * self.height as u32
+    }
+
+    /// First address after the buffer
+    /// Fails with `Error::OutOfMemory` if the buffer exceeds the 32 bit address space
+    pub fn end_addr(&self) -> Result<u32, Error> {
+        self.addr
+            .checked_add(self.size_bytes())
+            .ok_or(Error::OutOfMemory)
+    }
+
+    /// Checks if two buffers share memory
+    pub fn overlaps(&self, other: &ImageBuffer) -> bool {
+        (self.addr as u64) < other.end() && (other.addr as u64) < self.end()
+    }
+
+    // end address without overflow
+    fn end(&self) -> u64 {
+        self.addr as u64 + self.size_bytes() as u64
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        matches!(self.bpp, 1 | 2 | 4 | 8) && self.width > 0 && self.height > 0
+    }
+}
+
+/// Allocates image buffers in a region of the controller SDRAM
+/// Allocated buffers never overlap each other
+#[derive(Debug)]
+pub struct SdramAllocator {
+    start: u32,
+    end: u32,
+    buffers: Vec<ImageBuffer>,
+}
+
+impl SdramAllocator {
+    /// Creates a new allocator for the memory region start..end
+    pub fn new(start: u32, end: u32) -> Self {
+        SdramAllocator {
+            start: start.next_multiple_of(ALIGNMENT),
+            end,
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Allocates a new buffer at the first free address large enough
+    pub fn allocate(&mut self, width: u16, height: u16, bpp: u8) -> Result<ImageBuffer, Error> {
+        let mut buffer = ImageBuffer {
+            addr: self.start,
+            width,
+            height,
+            bpp,
+        };
+        if !buffer.is_valid() {
+            return Err(Error::InvalidImageBuffer);
+        }
+
+        // buffers are sorted by address, check the gaps in between
+        for allocated in &self.buffers {
+            if buffer.end_addr()? <= allocated.addr {
+                break;
+            }
+            buffer.addr = buffer
+                .addr
+                .max(allocated.end_addr()?.next_multiple_of(ALIGNMENT));
+        }
+
+        self.reserve(buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reserves a buffer at a fixed address
+    /// Fails if the buffer is outside of the managed region or overlaps an allocated buffer
+    pub fn reserve(&mut self, buffer: ImageBuffer) -> Result<(), Error> {
+        if !buffer.is_valid() {
+            return Err(Error::InvalidImageBuffer);
+        }
+        if buffer.addr < self.start || buffer.end_addr()? > self.end {
+            return Err(Error::OutOfMemory);
+        }
+        if self.buffers.iter().any(|b| b.overlaps(&buffer)) {
+            return Err(Error::MemoryOverlap);
+        }
+
+        let pos = self.buffers.partition_point(|b| b.addr < buffer.addr);
+        self.buffers.insert(pos, buffer);
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Allocated image buffer {}", buffer);
+
+        Ok(())
+    }
+
+    /// Releases a previously allocated buffer
+    pub fn free(&mut self, buffer: ImageBuffer) -> Result<(), Error> {
+        let pos = self
+            .buffers
+            .iter()
+            .position(|b| *b == buffer)
+            .ok_or(Error::InvalidImageBuffer)?;
+        self.buffers.remove(pos);
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Released image buffer {}", buffer);
+
+        Ok(())
+    }
+
+    /// All currently allocated buffers, sorted by address
+    pub fn allocated(&self) -> &[ImageBuffer] {
+        &self.buffers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_per_row_aligned_to_u16() {
+        let buffer = ImageBuffer {
+            addr: 0,
+            width: 3,
+            height: 2,
+            bpp: 4,
+        };
+        assert_eq!(buffer.bytes_per_row(), 2);
+        assert_eq!(buffer.size_bytes(), 4);
+
+        let buffer = ImageBuffer {
+            addr: 0,
+            width: 3,
+            height: 2,
+            bpp: 8,
+        };
+        assert_eq!(buffer.bytes_per_row(), 4);
+    }
+
+    #[test]
+    fn test_allocate_sequential() {
+        let mut allocator = SdramAllocator::new(0x1002, 0x2000);
+        let a = allocator.allocate(10, 10, 8).unwrap();
+        let b = allocator.allocate(10, 10, 8).unwrap();
+        assert_eq!(a.addr, 0x1004);
+        assert_eq!(b.addr, 0x1004 + 100);
+        assert!(!a.overlaps(&b));
+    }
+
+    #[test]
+    fn test_free_reuses_gap() {
+        let mut allocator = SdramAllocator::new(0x1000, 0x2000);
+        let a = allocator.allocate(10, 10, 8).unwrap();
+        let b = allocator.allocate(10, 10, 8).unwrap();
+        allocator.free(a).unwrap();
+        let c = allocator.allocate(4, 4, 8).unwrap();
+        assert_eq!(c.addr, a.addr);
+        assert_eq!(allocator.allocated(), &[c, b]);
+        assert_eq!(allocator.free(a), Err(Error::InvalidImageBuffer));
+    }
+
+    #[test]
+    fn test_reserve_rejects_overlap() {
+        let mut allocator = SdramAllocator::new(0x1000, 0x2000);
+        let a = allocator.allocate(10, 10, 8).unwrap();
+        let overlapping = ImageBuffer {
+            addr: a.addr + 50,
+            ..a
+        };
+        assert_eq!(allocator.reserve(overlapping), Err(Error::MemoryOverlap));
+        let outside = ImageBuffer { addr: 0x1FF0, ..a };
+        assert_eq!(allocator.reserve(outside), Err(Error::OutOfMemory));
+    }
+
+    #[test]
+    fn test_end_addr_overflow() {
+        let buffer = ImageBuffer {
+            addr: u32::MAX - 10,
+            width: 10,
+            height: 10,
+            bpp: 8,
+        };
+        assert_eq!(buffer.end_addr(), Err(Error::OutOfMemory));
+        assert!(buffer.overlaps(&ImageBuffer {
+            addr: u32::MAX - 1,
+            ..buffer
+        }));
+
+        let mut allocator = SdramAllocator::new(0x1000, u32::MAX);
+        assert_eq!(allocator.reserve(buffer), Err(Error::OutOfMemory));
+    }
+
+    #[test]
+    fn test_allocate_out_of_memory() {
+        let mut allocator = SdramAllocator::new(0x1000, 0x1100);
+        assert!(allocator.allocate(16, 16, 8).is_ok());
+        assert_eq!(allocator.allocate(1, 1, 8), Err(Error::OutOfMemory));
+        assert_eq!(allocator.allocate(1, 1, 3), Err(Error::InvalidImageBuffer));
+    }
+}
@@ -6,6 +6,8 @@ use embedded_hal::{
     spi::{Operation, SpiDevice},
 };
 
+use crate::backoff::Backoff;
+
 #[cfg(feature = "defmt")]
 use defmt;
 
@@ -126,36 +128,20 @@ where
         self.timeout = timeout
     }
 
-    /*
-       Exponential backoff eventually switches to longer delay.
-       When ussed with FreeRtos Delay, longer delay allows for other tasks to
-       execute instead of busy-loop for longer screen operations
-    */
     fn wait_while_busy(&mut self) -> Result<(), Error> {
-        let timeout_us = self.timeout.as_micros() as u32;
-
-        // Cap max backoff so we won't overshoot timeout significantly
-        // Set approximately to free-rtos tick to allow for other tasks to run
-        const BACKOFF_CAP_US: u32 = 1000;
-
-        let mut delay_us = 200_u32;
-
-        // This is estimation of total wait time,
-        // Prone to under-estimating but good enough for what it is for
-        let mut accumulated_delay_us = 0_u32;
+        let mut backoff = Backoff::new(self.timeout, None);
 
         while self.busy.is_low().map_err(|_| Error::GPIOError)? {
-            if accumulated_delay_us > timeout_us {
+            let Some(delay) = backoff.next_delay() else {
                 #[cfg(feature = "defmt")]
-                defmt::warn!("Timeout while waiting, waited {}μs", timeout_us);
+                defmt::warn!(
+                    "Timeout while waiting, waited {}us",
+                    backoff.elapsed().as_micros()
+                );
 
                 return Err(Error::BusyTimeout);
-            }
-            self.delay.delay_us(delay_us);
-            accumulated_delay_us += delay_us;
-            if delay_us < BACKOFF_CAP_US {
-                delay_us *= 2;
-            }
+            };
+            self.delay.delay_us(delay.as_micros() as u32);
         }
 
         Ok(())
@@ -1,4 +1,4 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![warn(missing_docs)]
 
 //! IT8951 epaper driver for the waveshare 7.8in display
@@ -8,21 +8,44 @@
 #[macro_use]
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::{borrow::Borrow, marker::PhantomData};
 
+mod align;
 mod area_serializer;
+mod backoff;
+mod blit;
 mod command;
+pub mod diagnostics;
+pub mod dirty;
+pub mod double_buffer;
+pub mod ghosting;
+pub mod image_buffer;
 pub mod interface;
 pub mod memory_converter_settings;
+pub mod offscreen;
 pub mod origin;
 mod pixel_serializer;
+pub mod profiles;
+mod readback;
+pub mod refresh;
 mod register;
 mod serialization_helper;
+pub mod vcom;
+pub mod waveform_policy;
+pub mod waveform_table;
 
 use area_serializer::{AreaSerializer, AreaSerializerIterator};
-use memory_converter_settings::MemoryConverterSetting;
+use backoff::Backoff;
+use dirty::{DirtyRegion, DirtyRegions};
+use ghosting::{GhostingBudget, GhostingConfig};
+use image_buffer::{ImageBuffer, SdramAllocator};
+use memory_converter_settings::{MemoryConverterRotation, MemoryConverterSetting};
 use pixel_serializer::{convert_color_to_pixel_iterator, PixelSerializer};
+use profiles::PanelProfile;
+use vcom::Vcom;
+use waveform_policy::{AreaContent, DefaultWaveformPolicy, UpdateMode, WaveformPolicy};
+use waveform_table::WaveformTable;
 
 #[cfg(feature = "defmt")]
 use defmt;
@@ -34,6 +57,33 @@ pub enum Error {
     Interface(interface::Error),
     /// Timeout
     DisplayEngineTimeout,
+    /// Not enough free controller memory
+    OutOfMemory,
+    /// The image buffer overlaps a already allocated buffer
+    MemoryOverlap,
+    /// The image buffer is not allocated or has a unsupported format
+    InvalidImageBuffer,
+    /// The provided buffer is too small
+    BufferTooSmall,
+    /// The VCOM voltage is outside of the range accepted by the controller
+    InvalidVcom,
+    /// The VCOM read back after setting it differs from the set value
+    VcomMismatch,
+    /// The device info is not available, the driver was not initialized
+    NotInitialized,
+    /// A buffer is not aligned to u16
+    Misaligned,
+    /// The area is not within the display
+    InvalidArea,
+    /// A config value is out of range, see `Config::validate`
+    InvalidConfig,
+    /// The LUT loaded on the controller lacks the waveform, see `WaveformTable`
+    UnsupportedWaveform,
+    /// Several known panels have the reported size, but none matches the LUT version
+    AmbiguousPanel,
+    /// Writing to a std::io writer failed
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
 }
 impl From<interface::Error> for Error {
     fn from(e: interface::Error) -> Self {
@@ -47,13 +97,48 @@ pub struct Config {
     pub timeout_display_engine: core::time::Duration,
     /// Timeout for the busy pin
     pub timeout_interface: core::time::Duration,
-    /// Max buffer size in bytes for staging buffers
+    /// Size in bytes of the staging buffer, which is allocated once by init and attach
     /// The buffer should be large enough to at least contain the pixels of a complete row
     /// The buffer must be aligned to u16
     /// The used IT8951 interface must support to write a complete buffer at once
     pub max_buffer_size: usize,
-    /// Display rotation
+    /// Display rotation, can be changed later with `set_rotation`
     pub rotation: Rotation,
+    /// Mirroring of the display content in panel coordinates, applied after the rotation
+    /// It is combined with the mirroring of the panel origin
+    pub mirror: Mirror,
+    /// Size of the controller SDRAM in bytes, the SDRAM starts at `image_buffer::SDRAM_START`
+    /// Image buffers are allocated between the end of the default image buffer and the end of the SDRAM
+    pub memory_size: u32,
+    /// Max number of dirty regions tracked for `display_dirty`
+    /// If more regions are drawn, the closest regions are merged
+    pub dirty_regions: usize,
+    /// Dirty regions are merged, if at most this percentage of the merged region was not drawn
+    pub dirty_overdraw_percent: u8,
+    /// Selects the waveform for `UpdateMode::Auto` updates
+    pub waveform_policy: Box<dyn WaveformPolicy>,
+    /// Promote fast updates to cleansing refreshes once the ghosting budget of a area is exceeded
+    /// `None` (default) never changes the requested waveform
+    pub ghosting: Option<GhostingConfig>,
+    /// Monotonic clock used to measure the display engine timeout
+    /// Without a clock the elapsed time is estimated from the delays between polls,
+    /// which ignores the time spent on the interface
+    pub clock: Option<fn() -> core::time::Duration>,
+    /// Number of LUT engines refreshing areas in parallel
+    /// A update only waits for running updates if its area overlaps them or all engines are busy
+    /// With 1 (default) every update waits until the display engine is idle
+    pub lut_engines: u8,
+    /// Widen drawn areas to 4 pixel boundaries in panel coordinates before loading them
+    /// The added pixels are read back from the image buffer, which costs additional transfers
+    /// A2 updates are always widened to 32 pixel boundaries
+    pub load_alignment: bool,
+    /// Read the VCOM back after setting it, a different value fails with `Error::VcomMismatch`
+    pub verify_vcom: bool,
+    /// Handling of refreshed areas which are not within the display
+    pub area_policy: AreaPolicy,
+    /// Mode numbers of the waveforms in the LUT loaded on the controller
+    /// `None` (default) selects the table from the LUT version reported by the controller
+    pub waveform_table: Option<WaveformTable>,
 }
 
 impl Default for Config {
@@ -63,13 +148,40 @@ impl Default for Config {
             timeout_interface: core::time::Duration::from_secs(15),
             max_buffer_size: 1024,
             rotation: Rotation::Rotate0,
+            mirror: Mirror::None,
+            memory_size: 0x0080_0000,
+            dirty_regions: 8,
+            dirty_overdraw_percent: 25,
+            waveform_policy: Box::new(DefaultWaveformPolicy),
+            ghosting: None,
+            clock: None,
+            lut_engines: 1,
+            load_alignment: false,
+            verify_vcom: false,
+            area_policy: AreaPolicy::Reject,
+            waveform_table: None,
         }
     }
 }
 
+impl Config {
+    /// Checks the config values, done by init and attach
+    /// The staging buffer must be u16 aligned and hold at least one u16,
+    /// at least one LUT engine must be used and the overdraw is a percentage
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_buffer_size < 2 || !self.max_buffer_size.is_multiple_of(2) {
+            return Err(Error::InvalidConfig);
+        }
+        if self.lut_engines == 0 || self.dirty_regions == 0 || self.dirty_overdraw_percent > 100 {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(())
+    }
+}
+
 /// Device Info Struct
 /// Describes the connected display
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DevInfo {
     /// width in pixel of the connected panel
     pub panel_width: u16,
@@ -86,7 +198,7 @@ pub struct DevInfo {
 }
 
 /// Describes a area on the display
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AreaImgInfo {
     /// x position (left to right, 0 is top left corner)
     pub area_x: u16,
@@ -112,8 +224,33 @@ impl defmt::Format for AreaImgInfo {
     }
 }
 
+impl From<&AreaImgInfo> for Rectangle {
+    fn from(area: &AreaImgInfo) -> Self {
+        Rectangle::new(
+            Point::new(area.area_x as i32, area.area_y as i32),
+            Size::new(area.area_w as u32, area.area_h as u32),
+        )
+    }
+}
+
+impl TryFrom<&Rectangle> for AreaImgInfo {
+    type Error = Error;
+
+    /// Fails with `Error::InvalidArea` if the rectangle has negative or out of range coordinates
+    fn try_from(area: &Rectangle) -> Result<Self, Error> {
+        let convert = |value: i64| u16::try_from(value).map_err(|_| Error::InvalidArea);
+        Ok(AreaImgInfo {
+            area_x: convert(area.top_left.x as i64)?,
+            area_y: convert(area.top_left.y as i64)?,
+            area_w: convert(area.size.width as i64)?,
+            area_h: convert(area.size.height as i64)?,
+        })
+    }
+}
+
 /// See https://www.waveshare.com/w/upload/c/c4/E-paper-mode-declaration.pdf for full description
-#[derive(Debug, Clone, Copy)]
+/// The values are the standard mode numbers, the numbers sent to the controller are taken from the `WaveformTable`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u16)]
 pub enum WaveformMode {
@@ -138,6 +275,8 @@ pub enum WaveformMode {
 /// Sets hardware rotation used by controller
 /// This will perform approriate rotation for all public interfaces exposed by the driver
 /// Including bounding boxes, pixel, and image drawing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Rotation {
     /// No rotation
     Rotate0,
@@ -149,6 +288,18 @@ pub enum Rotation {
     Rotate270,
 }
 
+/// Handling of areas which are not within the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AreaPolicy {
+    /// Fail with `Error::InvalidArea`
+    #[default]
+    Reject,
+    /// Refresh only the visible part of the area, a invisible area is ignored
+    /// Loaded areas are always rejected, as their pixel data can not be clipped
+    Clip,
+}
+
 /// Normal Operation
 pub struct Run;
 /// The device is either in sleep or standby mode:
@@ -166,6 +317,15 @@ pub struct IT8951<IT8951Interface, TOrigin: Origin, State> {
     marker: core::marker::PhantomData<State>,
     origin: core::marker::PhantomData<TOrigin>,
     config: Config,
+    allocator: Option<SdramAllocator>,
+    dirty: DirtyRegions,
+    ghosting: Option<GhostingBudget>,
+    panel_mirror: Mirror,
+    profile: Option<&'static PanelProfile>,
+    // LUT engine bits and panel areas of updates started since the display engine was idle
+    in_flight: Vec<(u16, AreaImgInfo)>,
+    // staging buffer of Config::max_buffer_size, lent to the serializers by with_staging
+    staging: Vec<u8>,
 }
 
 impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin, TState>
@@ -178,6 +338,13 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin, TState>
             marker: PhantomData {},
             origin: PhantomData {},
             config: self.config,
+            allocator: self.allocator,
+            dirty: self.dirty,
+            ghosting: self.ghosting,
+            panel_mirror: self.panel_mirror,
+            profile: self.profile,
+            in_flight: self.in_flight,
+            staging: self.staging,
         }
     }
 }
@@ -193,6 +360,79 @@ impl<IT8951Interface: interface::IT8951Interface> IT8951<IT8951Interface, Origin
     }
 }
 
+impl<IT8951Interface: interface::IT8951Interface> IT8951<IT8951Interface, OriginDynamic, Off> {
+    /// Creates a new controller driver object for a known panel
+    /// The origin is taken from the profile, the rotation only if the config keeps the default `Rotate0`
+    /// Call init afterwards to initalize the controller
+    pub fn new_with_profile(
+        interface: IT8951Interface,
+        mut config: Config,
+        profile: &'static PanelProfile,
+    ) -> IT8951<IT8951Interface, OriginDynamic, Off> {
+        if config.rotation == Rotation::Rotate0 {
+            config.rotation = profile.rotation;
+        }
+        let mut it8951 = Self::new_with_origin(interface, config, OriginDynamic {});
+        it8951.panel_mirror = profile.mirror;
+        it8951.profile = Some(profile);
+        it8951
+    }
+
+    /// Initalize the driver and select the panel profile from the reported device info
+    /// The origin and driving capability are taken from the detected profile,
+    /// the rotation only if the config keeps the default `Rotate0`
+    /// Unknown panels keep the TopLeft origin, `profile` returns `None`
+    /// Fails with `Error::AmbiguousPanel` if several profiles have the panel size but none the LUT,
+    /// the origin is not guessed, select the profile with `new_with_profile` instead
+    /// VCOM should be given on your display, e.g. `Vcom::from_volts(-1.53)` for "VCOM -1.53V"
+    pub fn init_detect(
+        self,
+        vcom: Vcom,
+    ) -> Result<IT8951<IT8951Interface, OriginDynamic, Run>, Error> {
+        let mut it8951 = self.init_no_vcom_detect()?;
+        it8951.apply_vcom(vcom)?;
+        Ok(it8951)
+    }
+
+    /// Initalize the driver and select the panel profile from the reported device info
+    /// without setting VCOM, see init_no_vcom and init_detect
+    pub fn init_no_vcom_detect(self) -> Result<IT8951<IT8951Interface, OriginDynamic, Run>, Error> {
+        let mut it8951 = self.init_no_vcom()?;
+        if it8951.profile.is_some() {
+            return Ok(it8951);
+        }
+
+        let dev_info = it8951.info()?;
+        let profile = match profiles::detect(dev_info) {
+            Ok(Some(profile)) => profile,
+            Ok(None) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("Unknown panel, LUT {=str}", dev_info.lut_version);
+
+                return Ok(it8951);
+            }
+            Err(e) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("Ambiguous panel, unknown LUT {=str}", dev_info.lut_version);
+
+                return Err(e);
+            }
+        };
+
+        #[cfg(feature = "defmt")]
+        defmt::info!("Detected panel {=str}", profile.name);
+
+        if it8951.config.rotation == Rotation::Rotate0 {
+            it8951.config.rotation = profile.rotation;
+        }
+        it8951.panel_mirror = profile.mirror;
+        it8951.profile = Some(profile);
+        it8951.apply_profile(profile)?;
+
+        Ok(it8951)
+    }
+}
+
 impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     IT8951<IT8951Interface, TOrigin, Off>
 {
@@ -209,23 +449,23 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
             dev_info: None,
             marker: PhantomData {},
             origin: PhantomData {},
+            dirty: DirtyRegions::new(config.dirty_regions, config.dirty_overdraw_percent),
             config,
+            allocator: None,
+            ghosting: None,
+            panel_mirror: TOrigin::MIRROR,
+            profile: None,
+            in_flight: Vec::new(),
+            staging: Vec::new(),
         }
     }
 
     /// Initalize the driver and resets the display
-    /// VCOM should be given on your display
+    /// VCOM should be given on your display, e.g. `Vcom::from_volts(-1.53)` for "VCOM -1.53V"
     /// Since version 0.4.0, this function no longer resets the display
-    pub fn init(self, vcom: u16) -> Result<IT8951<IT8951Interface, TOrigin, Run>, Error> {
+    pub fn init(self, vcom: Vcom) -> Result<IT8951<IT8951Interface, TOrigin, Run>, Error> {
         let mut it8951 = self.init_no_vcom()?;
-
-        let current_vcom = it8951.get_vcom()?;
-        if vcom != current_vcom {
-            #[cfg(feature = "defmt")]
-            defmt::trace!("Overriding vcom, wanted {}, current {}", vcom, current_vcom);
-
-            it8951.set_vcom(vcom)?;
-        }
+        it8951.apply_vcom(vcom)?;
         Ok(it8951)
     }
 
@@ -239,6 +479,8 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     /// Verify this by reading VCOM after calling init_no_vcom and see that it has
     /// a sensible value (e.g. not 0x0000 or 0xFFFF)
     pub fn init_no_vcom(mut self) -> Result<IT8951<IT8951Interface, TOrigin, Run>, Error> {
+        self.config.validate()?;
+        self.staging = vec![0x00; self.config.max_buffer_size];
         self.interface.reset()?;
 
         let mut it8951 = self.into_state::<PowerDown>().sys_run()?;
@@ -248,6 +490,20 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
         // Enable Pack Write
         it8951.write_register(register::I80CPCR, 0x0001)?;
 
+        #[cfg(feature = "defmt")]
+        if let Some(profile) = it8951.profile {
+            if (dev_info.panel_width, dev_info.panel_height) != (profile.width, profile.height) {
+                defmt::warn!(
+                    "Panel profile {=str} expects {}x{}, controller reports {}x{}",
+                    profile.name,
+                    profile.width,
+                    profile.height,
+                    dev_info.panel_width,
+                    dev_info.panel_height,
+                );
+            }
+        }
+
         #[cfg(feature = "defmt")]
         defmt::info!(
             "Initialized screen Resolution {}x{}, LUT {=str}, FWV {=str} MA = {:x}",
@@ -260,6 +516,10 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
 
         it8951.dev_info = Some(dev_info);
 
+        if let Some(profile) = it8951.profile {
+            it8951.apply_profile(profile)?;
+        }
+
         Ok(it8951)
     }
 
@@ -269,22 +529,30 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
         mut interface: IT8951Interface,
         config: Config,
     ) -> Result<IT8951<IT8951Interface, OriginTopLeft, Run>, Error> {
+        config.validate()?;
         interface.set_busy_timeout(config.timeout_interface);
+        let max_buffer_size = config.max_buffer_size;
 
         let mut it8951: IT8951<IT8951Interface, OriginTopLeft, Run> = IT8951 {
             interface,
             dev_info: None,
             marker: PhantomData {},
             origin: PhantomData {},
+            dirty: DirtyRegions::new(config.dirty_regions, config.dirty_overdraw_percent),
             config,
+            allocator: None,
+            ghosting: None,
+            panel_mirror: Mirror::None,
+            profile: None,
+            in_flight: Vec::new(),
+            staging: vec![0x00; max_buffer_size],
         }
         .sys_run()?;
 
         it8951.dev_info = Some(it8951.get_system_info()?);
 
         #[cfg(feature = "defmt")]
-        {
-            let dev_info = it8951.dev_info.as_ref().unwrap();
+        if let Some(dev_info) = it8951.dev_info.as_ref() {
             defmt::info!(
                 "Attached screen Resolution {}x{}, LUT {=str}, FWV {=str}",
                 dev_info.panel_width,
@@ -302,15 +570,111 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     IT8951<IT8951Interface, TOrigin, Run>
 {
     /// Get the Device information
-    pub fn get_dev_info(&self) -> DevInfo {
-        self.dev_info.clone().unwrap()
+    pub fn get_dev_info(&self) -> Result<DevInfo, Error> {
+        self.info().cloned()
+    }
+
+    /// Device info read during init or attach
+    fn info(&self) -> Result<&DevInfo, Error> {
+        self.dev_info.as_ref().ok_or(Error::NotInitialized)
+    }
+
+    /// Panel profile given to new_with_profile or detected by init_detect
+    pub fn profile(&self) -> Option<&'static PanelProfile> {
+        self.profile
+    }
+
+    /// Waveform table of the LUT, given by `Config::waveform_table`, the panel profile
+    /// or selected from the LUT version
+    pub fn waveform_table(&self) -> WaveformTable {
+        match (self.config.waveform_table, self.profile, &self.dev_info) {
+            (Some(table), _, _) => table,
+            (None, Some(profile), _) => profile.waveform_table,
+            (None, None, Some(dev_info)) => WaveformTable::from_lut_version(&dev_info.lut_version),
+            (None, None, None) => WaveformTable::default(),
+        }
+    }
+
+    fn apply_profile(&mut self, profile: &PanelProfile) -> Result<(), Error> {
+        if profile.enhance_driving_capability {
+            self.enhance_driving_capability()?;
+        }
+        Ok(())
+    }
+
+    fn apply_vcom(&mut self, vcom: Vcom) -> Result<(), Error> {
+        #[cfg(feature = "defmt")]
+        if let Some(profile) = self.profile {
+            let (min, max) = profile.vcom_range_mv;
+            if !(min..=max).contains(&vcom.millivolts()) {
+                defmt::warn!(
+                    "VCOM {} is outside the typical range of {=str}",
+                    vcom,
+                    profile.name
+                );
+            }
+        }
+
+        let current_vcom = self.get_vcom()?;
+        if vcom != current_vcom {
+            #[cfg(feature = "defmt")]
+            defmt::trace!("Overriding vcom, wanted {}, current {}", vcom, current_vcom);
+
+            self.set_vcom(vcom)?;
+        }
+        Ok(())
     }
 
     /// Overwrites the reported default buffer address reported by the it8951
+    /// All allocated image buffers are released
     pub fn overwrite_default_buffer_address(&mut self, address: u32) {
         if let Some(dev_info) = &mut self.dev_info {
             dev_info.memory_address = address;
         }
+        self.allocator = None;
+    }
+
+    /// Get the default image buffer used for drawing and display updates
+    pub fn image_buffer(&self) -> Result<ImageBuffer, Error> {
+        let dev_info = self.info()?;
+        Ok(ImageBuffer {
+            addr: dev_info.memory_address,
+            width: dev_info.panel_width,
+            height: dev_info.panel_height,
+            bpp: 8,
+        })
+    }
+
+    /// Allocates a image buffer in the controller SDRAM after the default image buffer
+    pub fn alloc_image_buffer(
+        &mut self,
+        width: u16,
+        height: u16,
+        bpp: u8,
+    ) -> Result<ImageBuffer, Error> {
+        self.allocator()?.allocate(width, height, bpp)
+    }
+
+    /// Reserves a image buffer at a fixed address in the controller SDRAM
+    /// Fails if the buffer overlaps the default image buffer or another allocated buffer
+    pub fn reserve_image_buffer(&mut self, buffer: ImageBuffer) -> Result<(), Error> {
+        self.allocator()?.reserve(buffer)
+    }
+
+    /// Releases a allocated image buffer
+    pub fn free_image_buffer(&mut self, buffer: ImageBuffer) -> Result<(), Error> {
+        self.allocator()?.free(buffer)
+    }
+
+    fn allocator(&mut self) -> Result<&mut SdramAllocator, Error> {
+        if self.allocator.is_none() {
+            let start = self.image_buffer()?.end_addr()?;
+            let end = image_buffer::SDRAM_START
+                .checked_add(self.config.memory_size)
+                .ok_or(Error::OutOfMemory)?;
+            self.allocator = Some(SdramAllocator::new(start, end));
+        }
+        self.allocator.as_mut().ok_or(Error::NotInitialized)
     }
 
     /// Increases the driver strength
@@ -347,11 +711,18 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
         image_settings: TMemoryConverterSetting,
         data: &[u8],
     ) -> Result<(), Error> {
+        let image_settings = image_settings.borrow();
+        let panel = self.panel_size()?;
+        let bytes_per_row =
+            (panel.width * image_settings.bit_per_pixel.bits()).div_ceil(16) as usize * 2;
+        if data.len() > bytes_per_row * panel.height as usize {
+            return Err(Error::InvalidArea);
+        }
+
         self.set_target_memory_addr(target_mem_addr)?;
 
         self.interface.write_command(command::IT8951_TCON_LD_IMG)?;
-        self.interface
-            .write_data(image_settings.borrow().into_arg())?;
+        self.interface.write_data(image_settings.into_arg())?;
 
         self.interface.write_multi_data(data)?;
 
@@ -367,6 +738,7 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     /// Memory Address should be read from the dev_info struct
     /// ImageSettings define the layout of the data buffer
     /// AreaInfo describes the frame buffer area which should be updated
+    /// The area must be within the panel rotated by the image settings, otherwise `Error::InvalidArea` is returned
     pub fn load_image_area<TMemoryConverterSetting: Borrow<MemoryConverterSetting>>(
         &mut self,
         target_mem_addr: u32,
@@ -375,12 +747,19 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
         data: &[u8],
     ) -> Result<(), Error> {
         // Note that area_info does not need to be rotated here, as controller hw will do the rotation
+        let image_settings = image_settings.borrow();
+        let rotation = Rotation::from(&image_settings.rotation);
+        let bounds = Orientation::new(rotation, Mirror::None).display_size(self.panel_size()?);
+        if !area_within(area_info, bounds) {
+            return Err(Error::InvalidArea);
+        }
+
         self.set_target_memory_addr(target_mem_addr)?;
 
         self.interface.write_command_with_args(
             command::IT8951_TCON_LD_IMG_AREA,
             &[
-                image_settings.borrow().into_arg(),
+                image_settings.into_arg(),
                 area_info.area_x,
                 area_info.area_y,
                 area_info.area_w,
@@ -412,8 +791,12 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     // buffer functions -------------------------------------------------------------------------------------------------
 
     /// Reads the given memory address from the controller ram into data
-    /// Buffer needs to be aligned to u16!
+    /// Buffer needs to be aligned to u16, otherwise `Error::Misaligned` is returned
     pub fn memory_burst_read(&mut self, memory_address: u32, data: &mut [u8]) -> Result<(), Error> {
+        if !data.len().is_multiple_of(2) {
+            return Err(Error::Misaligned);
+        }
+
         let args = [
             memory_address as u16,
             (memory_address >> 16) as u16,
@@ -443,12 +826,16 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     }
 
     /// Writes a buffer of u16 values to the given memory address in the controller ram
-    /// Buffer needs to be aligned to u16!
+    /// Buffer needs to be aligned to u16, otherwise `Error::Misaligned` is returned
     pub fn memory_burst_write(
         &mut self,
         memory_address: u32,
         data: &mut [u8],
     ) -> Result<(), Error> {
+        if !data.len().is_multiple_of(2) {
+            return Err(Error::Misaligned);
+        }
+
         let args = [
             memory_address as u16,
             (memory_address >> 16) as u16,
@@ -471,35 +858,71 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
         Ok(())
     }
 
+    /// Lends the staging buffer of `Config::max_buffer_size` bytes to f
+    /// The buffer is taken from the driver while f runs, f must not use the staging buffer again
+    pub(crate) fn with_staging<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Self, &mut [u8]) -> Result<(), Error>,
+    {
+        let mut staging = core::mem::take(&mut self.staging);
+        // drivers which were not initialized allocate the buffer on first use
+        if staging.len() < self.config.max_buffer_size {
+            staging.resize(self.config.max_buffer_size, 0x00);
+        }
+        let result = f(self, &mut staging[..self.config.max_buffer_size]);
+        self.staging = staging;
+        result
+    }
+
     // display functions ------------------------------------------------------------------------------------------------
 
     /// Refresh a specific area of the display with the frame buffer content
     /// A usecase specific wafeform must be selected by the user
-    /// Note that this will panic if area_info is outside of screen bounding box when rotation is enabled
+    /// Areas outside of the display fail with `Error::InvalidArea` with `AreaPolicy::Reject` (default),
+    /// `AreaPolicy::Clip` refreshes only the visible part
     pub fn display_area(
         &mut self,
         area_info: &AreaImgInfo,
         mode: WaveformMode,
     ) -> Result<(), Error> {
-        let area_info = self.rotate_area_info(area_info);
+        self.display_area_with(area_info, mode, true)
+    }
 
-        self.wait_for_display_ready()?;
+    fn display_area_with(
+        &mut self,
+        area_info: &AreaImgInfo,
+        mode: WaveformMode,
+        screen_cleansing: bool,
+    ) -> Result<(), Error> {
+        let Some(area_info) = self.clip_area(area_info)? else {
+            return Ok(());
+        };
+        let table = self.waveform_table();
+        if !table.supports(mode) {
+            return Err(Error::UnsupportedWaveform);
+        }
+        let area_info = self.align_display_area(self.panel_area_info(&area_info)?, mode)?;
+        let (area_info, number) =
+            self.apply_ghosting_budget(area_info, mode, screen_cleansing, &table)?;
+
+        let active = self.wait_for_lut_engine(&area_info)?;
         let args = [
             area_info.area_x,
             area_info.area_y,
             area_info.area_w,
             area_info.area_h,
-            mode as u16,
+            number,
         ];
 
         self.interface
             .write_command_with_args(command::USDEF_I80_CMD_DPY_AREA, &args)?;
+        self.track_update(area_info, active)?;
 
         #[cfg(feature = "defmt")]
         defmt::trace!(
-            "Refreshed display area {} with mode {}",
+            "Refreshed display area {} with mode number {}",
             area_info,
-            mode.clone()
+            number
         );
 
         Ok(())
@@ -507,38 +930,289 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
 
     /// Refresh a specific area of the display from a dedicated frame buffer
     /// A usecase specific wafeform must be selected by the user
+    /// Areas outside of the display are handled by `Config::area_policy` like in `display_area`
     pub fn display_area_buf(
         &mut self,
         area_info: &AreaImgInfo,
         mode: WaveformMode,
         target_mem_addr: u32,
     ) -> Result<(), Error> {
-        let area_info = self.rotate_area_info(area_info);
+        self.display_area_buf_with(area_info, mode, target_mem_addr, true)
+    }
+
+    fn display_area_buf_with(
+        &mut self,
+        area_info: &AreaImgInfo,
+        mode: WaveformMode,
+        target_mem_addr: u32,
+        screen_cleansing: bool,
+    ) -> Result<(), Error> {
+        let Some(area_info) = self.clip_area(area_info)? else {
+            return Ok(());
+        };
+        let table = self.waveform_table();
+        if !table.supports(mode) {
+            return Err(Error::UnsupportedWaveform);
+        }
+        let area_info = self.align_display_area(self.panel_area_info(&area_info)?, mode)?;
+        let (area_info, number) =
+            self.apply_ghosting_budget(area_info, mode, screen_cleansing, &table)?;
+
+        let active = self.wait_for_lut_engine(&area_info)?;
         let args = [
             area_info.area_x,
             area_info.area_y,
             area_info.area_w,
             area_info.area_h,
-            mode as u16,
+            number,
             target_mem_addr as u16,
             (target_mem_addr >> 16) as u16,
         ];
-
-        self.wait_for_display_ready()?;
         self.interface
             .write_command_with_args(command::USDEF_I80_CMD_DPY_BUF_AREA, &args)?;
+        self.track_update(area_info, active)?;
 
         #[cfg(feature = "defmt")]
         defmt::trace!(
-            "Refreshed display area {} with mode {} from addr {}",
+            "Refreshed display area {} with mode number {} from addr {}",
             area_info,
-            mode,
+            number,
             target_mem_addr
         );
 
         Ok(())
     }
 
+    /// Applies the ghosting budget to a update in panel coordinates
+    /// Returns the area and the mode number of the waveform, which may be promoted
+    /// Flagged updates like hardware fills must not be extended to the whole screen
+    fn apply_ghosting_budget(
+        &mut self,
+        area_info: AreaImgInfo,
+        mode: WaveformMode,
+        screen_cleansing: bool,
+        table: &WaveformTable,
+    ) -> Result<(AreaImgInfo, u16), Error> {
+        let Some(config) = self.config.ghosting else {
+            return Ok((area_info, table.number(mode)?));
+        };
+        let info = self.info()?;
+        let (pw, ph) = (info.panel_width, info.panel_height);
+        let (area_info, _, number) = self
+            .ghosting
+            .get_or_insert_with(|| GhostingBudget::new(config, pw, ph))
+            .update(area_info, mode, screen_cleansing, table)?;
+        Ok((area_info, number))
+    }
+
+    /// Counters of the ghosting budget, if `Config::ghosting` is set
+    /// The counters are created with the first display update
+    pub fn ghosting_budget(&self) -> Option<&GhostingBudget> {
+        self.ghosting.as_ref()
+    }
+
+    /// Resets all ghosting counters, e.g. after a full refresh
+    pub fn reset_ghosting_budget(&mut self) {
+        if let Some(ghosting) = self.ghosting.as_mut() {
+            ghosting.reset();
+        }
+    }
+
+    /// Refresh all areas drawn since the last call and clear them
+    /// Each merged dirty region is refreshed with a separate display_area call
+    /// With `UpdateMode::Auto` the waveform policy selects the waveform for every region,
+    /// waveforms missing in the LUT are replaced by GC16
+    /// If GC16 is missing as well, the region is dropped and `Error::UnsupportedWaveform` returned
+    pub fn display_dirty(&mut self, mode: impl Into<UpdateMode>) -> Result<(), Error> {
+        let mode = mode.into();
+        let table = self.waveform_table();
+        // check a explicit waveform before taking the regions
+        if let UpdateMode::Mode(mode) = mode {
+            table.number(mode)?;
+        }
+        while let Some(region) = self.dirty.pop() {
+            let mode = match mode {
+                UpdateMode::Mode(mode) => mode,
+                UpdateMode::Auto => match self.config.waveform_policy.select(&region.content) {
+                    Some(mode) if table.supports(mode) => mode,
+                    Some(_) if table.supports(WaveformMode::GrayscaleClearing16) => {
+                        WaveformMode::GrayscaleClearing16
+                    }
+                    // retrying the region would fail again
+                    Some(_) => return Err(Error::UnsupportedWaveform),
+                    None => continue,
+                },
+            };
+            let result = AreaImgInfo::try_from(&region.area)
+                .and_then(|area_info| self.display_area(&area_info, mode));
+            if let Err(e) = result {
+                // keep the region for the next try
+                self.dirty.add_region(region);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Areas drawn since the last `display_dirty` call, in display coordinates
+    pub fn dirty_regions(&self) -> &[DirtyRegion] {
+        self.dirty.regions()
+    }
+
+    /// Marks a area as dirty, e.g. after writing the image buffer with `load_image_area`
+    /// The area is clipped to the display
+    /// The drawn content is unknown, `UpdateMode::Auto` therefore uses a waveform for all gray levels
+    pub fn mark_dirty(&mut self, area: &Rectangle) {
+        self.add_dirty(area, AreaContent::UNKNOWN);
+    }
+
+    fn add_dirty(&mut self, area: &Rectangle, content: AreaContent) {
+        let area = area.intersection(&self.bounding_box());
+        self.dirty.add(area, content);
+    }
+
+    /// Forget all dirty regions without refreshing them
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+
+    /// Current display rotation
+    pub fn rotation(&self) -> Rotation {
+        self.config.rotation
+    }
+
+    /// Changes the display rotation, e.g. when the device is turned
+    /// The frame buffer content is not rotated and must be redrawn,
+    /// dirty regions are dropped since they are in the coordinates of the old rotation
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.set_orientation(Orientation::new(rotation, self.config.mirror));
+    }
+
+    /// Current rotation and mirroring, without the mirroring of the panel origin
+    pub fn orientation(&self) -> Orientation {
+        Orientation::new(self.config.rotation, self.config.mirror)
+    }
+
+    /// Changes the display rotation and mirroring, see set_rotation
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Orientation {} -> {}", self.orientation(), orientation);
+
+        self.config.rotation = orientation.rotation;
+        self.config.mirror = orientation.mirror;
+        self.dirty.clear();
+    }
+
+    /// Orientation of the display content on the panel, including the panel origin
+    fn panel_orientation(&self) -> Orientation {
+        self.orientation().mirrored(self.panel_mirror)
+    }
+
+    /// Size of the panel in pixels
+    fn panel_size(&self) -> Result<Size, Error> {
+        let info = self.info()?;
+        Ok(Size::new(info.panel_width as u32, info.panel_height as u32))
+    }
+
+    /// Mirroring of loaded pixels, the controller applies the rotation
+    fn load_flip(&self) -> Result<Flip, Error> {
+        Ok(self.panel_orientation().load_flip(self.panel_size()?))
+    }
+
+    /// Rotation of the memory converter for loaded pixels
+    fn load_rotation(&self) -> MemoryConverterRotation {
+        (&self.panel_orientation().rotation).into()
+    }
+
+    /// Changes the display rotation and clears the display to a single color
+    pub fn set_rotation_and_clear(
+        &mut self,
+        rotation: Rotation,
+        color: Gray4,
+        mode: WaveformMode,
+    ) -> Result<(), Error> {
+        self.set_rotation(rotation);
+        self.clear(color)?;
+        self.display(mode)?;
+        self.dirty.clear();
+        Ok(())
+    }
+
+    /// Fill a specific area of the display with a single color and refresh it
+    /// The display engine generates the fill value, no pixel data is transferred
+    /// The image buffer is not changed, later refreshes of the area show its content again
+    /// A usecase specific wafeform must be selected by the user
+    pub fn hardware_fill(
+        &mut self,
+        area_info: &AreaImgInfo,
+        color: Gray4,
+        mode: WaveformMode,
+    ) -> Result<(), Error> {
+        // lower byte holds the 8bpp fill value
+        let raw_color = color.luma() as u16;
+        self.update_with_flags(
+            register::UP1SR_FILL_EN,
+            0x00FF,
+            (raw_color << 4) | raw_color,
+            |it8951| it8951.display_area_with(area_info, mode, false),
+        )?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Hardware filled area {} with {}", area_info, raw_color);
+
+        Ok(())
+    }
+
+    /// Refresh a specific area of the display and alpha blend the frame buffer content
+    /// with the current panel content
+    /// An alpha of 0xFF shows only the frame buffer content, 0x00 keeps the panel content
+    pub fn display_area_blended(
+        &mut self,
+        area_info: &AreaImgInfo,
+        mode: WaveformMode,
+        alpha: u8,
+    ) -> Result<(), Error> {
+        self.update_with_flags(
+            register::UP1SR_ALPHA_BLEND_EN,
+            0xFF00,
+            (alpha as u16) << 8,
+            |it8951| it8951.display_area_with(area_info, mode, false),
+        )?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Blended display area {} with alpha {}", area_info, alpha);
+
+        Ok(())
+    }
+
+    /// Refresh a specific area of the display from a dedicated frame buffer
+    /// and alpha blend it with the current panel content
+    /// An alpha of 0xFF shows only the frame buffer content, 0x00 keeps the panel content
+    pub fn display_area_buf_blended(
+        &mut self,
+        area_info: &AreaImgInfo,
+        mode: WaveformMode,
+        target_mem_addr: u32,
+        alpha: u8,
+    ) -> Result<(), Error> {
+        self.update_with_flags(
+            register::UP1SR_ALPHA_BLEND_EN,
+            0xFF00,
+            (alpha as u16) << 8,
+            |it8951| it8951.display_area_buf_with(area_info, mode, target_mem_addr, false),
+        )?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!(
+            "Blended display area {} from addr {} with alpha {}",
+            area_info,
+            target_mem_addr,
+            alpha
+        );
+
+        Ok(())
+    }
+
     /// Refresh the full E-Ink display with the frame buffer content
     /// A usecase specific wafeform must be selected by the user
     pub fn display(&mut self, mode: WaveformMode) -> Result<(), Error> {
@@ -559,15 +1233,60 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     // misc  ------------------------------------------------------------------------------------------------
 
     fn wait_for_display_ready(&mut self) -> Result<(), Error> {
-        let timeout = self.config.timeout_display_engine.as_micros() as u64;
-        let mut counter = 0u64;
+        let mut backoff = Backoff::new(self.config.timeout_display_engine, self.config.clock);
         while 0 != self.read_register(register::LUTAFSR)? {
-            if counter > timeout {
+            let Some(delay) = backoff.next_delay() else {
+                #[cfg(feature = "defmt")]
+                defmt::warn!(
+                    "Display engine timeout, waited {}us",
+                    backoff.elapsed().as_micros()
+                );
+
                 return Err(Error::DisplayEngineTimeout);
-            }
-            counter += 1;
-            self.interface.delay(core::time::Duration::from_micros(1))?;
+            };
+            self.interface.delay(delay)?;
         }
+        self.in_flight.clear();
+        Ok(())
+    }
+
+    /// Waits until a LUT engine can refresh the panel area
+    /// Waits for all running updates, if the area overlaps one of them or no engine is free
+    /// Returns the LUT engines busy before the update
+    fn wait_for_lut_engine(&mut self, area_info: &AreaImgInfo) -> Result<u16, Error> {
+        if self.config.lut_engines <= 1 {
+            self.wait_for_display_ready()?;
+            return Ok(0);
+        }
+
+        let active = self.read_register(register::LUTAFSR)?;
+        // updates whose engines are idle again have finished
+        self.in_flight.retain(|(engines, _)| engines & active != 0);
+
+        let overlaps = self.in_flight.iter().any(|(_, other)| {
+            let (a, b) = (Rectangle::from(area_info), Rectangle::from(other));
+            !a.intersection(&b).is_zero_sized()
+        });
+        if overlaps || active.count_ones() >= self.config.lut_engines as u32 {
+            #[cfg(feature = "defmt")]
+            defmt::trace!("Waiting for LUT engines {:x}, overlap {}", active, overlaps);
+
+            self.wait_for_display_ready()?;
+            return Ok(0);
+        }
+        Ok(active)
+    }
+
+    /// Records the LUT engine of a started update, from the engines busy before it
+    /// If no new engine is busy, the update is kept until the display engine is idle
+    fn track_update(&mut self, area_info: AreaImgInfo, active: u16) -> Result<(), Error> {
+        if self.config.lut_engines <= 1 {
+            return Ok(());
+        }
+
+        let started = self.read_register(register::LUTAFSR)? & !active;
+        let engines = if started == 0 { u16::MAX } else { started };
+        self.in_flight.push((engines, area_info));
         Ok(())
     }
 
@@ -614,13 +1333,10 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
         })
     }
 
+    /// Swaps the bytes of each u16, a trailing odd byte is left unchanged
     fn convert_endianness(buffer: &mut [u8]) {
-        if !buffer.len().is_multiple_of(2) {
-            panic!("Buffer needs to be align on u16");
-        }
-
-        for i in (0..buffer.len() - 1).step_by(2) {
-            buffer.swap(i, i + 1)
+        for word in buffer.chunks_exact_mut(2) {
+            word.swap(0, 1)
         }
     }
 
@@ -636,10 +1352,10 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     /// Get the current VCOM setting for the panel
     /// This should normally be set at initialising either by passing a value to init
     /// or will be loaded automatically by the IT8951 from OTP (one time programmable memory)
-    pub fn get_vcom(&mut self) -> Result<u16, Error> {
+    pub fn get_vcom(&mut self) -> Result<Vcom, Error> {
         self.interface.write_command(command::USDEF_I80_CMD_VCOM)?;
         self.interface.write_data(0x0000)?;
-        let vcom = self.interface.read_data()?;
+        let vcom = Vcom::from_raw(self.interface.read_data()?);
 
         #[cfg(feature = "defmt")]
         defmt::trace!("CURRENT VCOM = {}", vcom);
@@ -650,54 +1366,147 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     /// Sets the VCOM for the panel
     /// Set this with extreme caution. Using the wrong value can damage your panel. This will normally
     /// be set during initialising.
-    pub fn set_vcom(&mut self, vcom: u16) -> Result<(), Error> {
+    pub fn set_vcom(&mut self, vcom: Vcom) -> Result<(), Error> {
         self.interface.write_command(command::USDEF_I80_CMD_VCOM)?;
         self.interface.write_data(0x0001)?;
-        self.interface.write_data(vcom)?;
+        self.interface.write_data(vcom.millivolts())?;
 
         #[cfg(feature = "defmt")]
         defmt::trace!("VCOM Set {}", vcom);
 
-        Ok(())
-    }
+        if self.config.verify_vcom {
+            let current_vcom = self.get_vcom()?;
+            if current_vcom != vcom {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("VCOM mismatch, set {}, read {}", vcom, current_vcom);
 
-    fn read_register(&mut self, reg: u16) -> Result<u16, Error> {
-        self.interface.write_command(command::IT8951_TCON_REG_RD)?;
-        self.interface.write_data(reg)?;
-        let data = self.interface.read_data()?;
-        Ok(data)
-    }
+                return Err(Error::VcomMismatch);
+            }
+        }
 
-    fn write_register(&mut self, reg: u16, data: u16) -> Result<(), Error> {
-        self.interface.write_command(command::IT8951_TCON_REG_WR)?;
-        self.interface.write_data(reg)?;
-        self.interface.write_data(data)?;
         Ok(())
     }
 
-    fn rotate_area_info(&self, area: &AreaImgInfo) -> AreaImgInfo {
-        use Rotation::*;
-        let info = self.dev_info.as_ref().expect("Unable to load device info");
-        let (pw, ph) = (info.panel_width, info.panel_height);
+    /// Reads the panel temperature in °C measured by the controller sensor
+    /// A forced temperature does not change the measured value
+    pub fn get_temperature(&mut self) -> Result<i8, Error> {
+        self.interface.write_command(command::USDEF_I80_CMD_TEMP)?;
+        self.interface.write_data(command::TEMP_GET)?;
+        // the controller answers with the measured and the forced temperature
+        let measured = self.interface.read_data()? as i16;
+        let _forced = self.interface.read_data()?;
 
-        let (x, y, w, h) = (area.area_x, area.area_y, area.area_w, area.area_h);
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Temperature = {}", measured);
+
+        Ok(measured.clamp(i8::MIN as i16, i8::MAX as i16) as i8)
+    }
 
-        let (x, y, w, h) = match self.config.rotation {
-            Rotate0 => (x, y, w, h),
-            Rotate90 => (y, ph - w - x, h, w),
-            Rotate180 => (pw - w - x, ph - h - y, w, h),
-            Rotate270 => (pw - h - y, x, h, w),
+    /// Forces the temperature in °C used to select the waveform timing
+    /// `None` re-reads the sensor and forces the measured temperature, since the release
+    /// of a forced temperature is not verified. Call it again to follow later temperature changes
+    /// Useful if the controller sensor does not measure the panel temperature, e.g. next to a warm MCU
+    pub fn set_forced_temperature(&mut self, temperature: Option<i8>) -> Result<(), Error> {
+        let temperature = match temperature {
+            Some(temperature) => temperature,
+            None => self.get_temperature()?,
         };
+        self.interface.write_command_with_args(
+            command::USDEF_I80_CMD_TEMP,
+            &[command::TEMP_SET_FORCED, temperature as i16 as u16],
+        )?;
 
-        AreaImgInfo {
-            area_x: x,
-            area_y: y,
-            area_w: w,
-            area_h: h,
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Forced temperature {}", temperature);
+
+        Ok(())
+    }
+
+    fn read_register(&mut self, reg: u16) -> Result<u16, Error> {
+        self.interface.write_command(command::IT8951_TCON_REG_RD)?;
+        self.interface.write_data(reg)?;
+        let data = self.interface.read_data()?;
+        Ok(data)
+    }
+
+    fn write_register(&mut self, reg: u16, data: u16) -> Result<(), Error> {
+        self.interface.write_command(command::IT8951_TCON_REG_WR)?;
+        self.interface.write_data(reg)?;
+        self.interface.write_data(data)?;
+        Ok(())
+    }
+
+    /// Runs a display update with additional update flags and LUT0ABFRV value bits
+    /// The flags stay active until the display engine finished the update
+    fn update_with_flags(
+        &mut self,
+        flags: u16,
+        value_mask: u16,
+        value: u16,
+        update: impl FnOnce(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.wait_for_display_ready()?;
+
+        let abfrv = self.read_register(register::LUT0ABFRV)?;
+        self.write_register(register::LUT0ABFRV, (abfrv & !value_mask) | value)?;
+
+        let up1sr = self.read_register(register::UP1SR + 2)?;
+        self.write_register(register::UP1SR + 2, up1sr | flags)?;
+
+        let result = update(self).and_then(|_| self.wait_for_display_ready());
+
+        let up1sr = self.read_register(register::UP1SR + 2)?;
+        self.write_register(register::UP1SR + 2, up1sr & !flags)?;
+
+        result
+    }
+
+    /// Transforms a area in display coordinates to panel coordinates
+    /// Fails if the area is not within the display
+    fn panel_area_info(&self, area: &AreaImgInfo) -> Result<AreaImgInfo, Error> {
+        let panel = self.panel_size()?;
+        if !area_within(area, self.panel_orientation().display_size(panel)) {
+            return Err(Error::InvalidArea);
+        }
+        let area = self
+            .panel_orientation()
+            .area_to_panel(&Rectangle::from(area), panel);
+        AreaImgInfo::try_from(&area)
+    }
+
+    /// Applies the `AreaPolicy` to a area in display coordinates
+    /// Returns `None` if a clipped area is not visible
+    fn clip_area(&self, area: &AreaImgInfo) -> Result<Option<AreaImgInfo>, Error> {
+        let display = self.panel_orientation().display_size(self.panel_size()?);
+        if area_within(area, display) {
+            return Ok(Some(*area));
+        }
+        match self.config.area_policy {
+            AreaPolicy::Reject => Err(Error::InvalidArea),
+            AreaPolicy::Clip => {
+                let clipped =
+                    Rectangle::from(area).intersection(&Rectangle::new(Point::zero(), display));
+
+                if clipped.is_zero_sized() {
+                    return Ok(None);
+                }
+                let clipped = AreaImgInfo::try_from(&clipped)?;
+
+                #[cfg(feature = "defmt")]
+                defmt::trace!("Clipped area {} to {}", area, clipped);
+
+                Ok(Some(clipped))
+            }
         }
     }
 }
 
+/// True if the area is within a display of the given size
+fn area_within(area: &AreaImgInfo, size: Size) -> bool {
+    area.area_x as u32 + area.area_w as u32 <= size.width
+        && area.area_y as u32 + area.area_h as u32 <= size.height
+}
+
 impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
     IT8951<IT8951Interface, TOrigin, PowerDown>
 {
@@ -717,31 +1526,22 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
 
 use embedded_graphics_core::{pixelcolor::Gray4, prelude::*, primitives::Rectangle};
 
-use crate::origin::{Origin, OriginTopLeft};
+use crate::origin::{Flip, Mirror, Orientation, Origin, OriginDynamic, OriginTopLeft};
 
-impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin> DrawTarget
-    for IT8951<IT8951Interface, TOrigin, Run>
+impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
+    IT8951<IT8951Interface, TOrigin, Run>
 {
-    type Color = Gray4;
-
-    type Error = Error;
-
-    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
-        let size = self.size();
-
-        self.fill_solid(
-            &Rectangle::new(
-                Point::zero(),
-                Size {
-                    width: size.width,
-                    height: size.height,
-                },
-            ),
-            color,
-        )
+    fn default_memory_address(&self) -> Result<u32, Error> {
+        Ok(self.info()?.memory_address)
     }
 
-    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+    /// Fills a area of the image buffer at memory_address with a single color
+    fn fill_solid_at(
+        &mut self,
+        memory_address: u32,
+        area: &Rectangle,
+        color: Gray4,
+    ) -> Result<(), Error> {
         // only update visible content
         let area = area.intersection(&self.bounding_box());
         // if the area is zero sized, skip drawing
@@ -749,62 +1549,86 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin> DrawTarget
             return Ok(());
         }
 
-        let a = AreaSerializer::new(area, color, self.config.max_buffer_size);
-        let area_iter = AreaSerializerIterator::new(&a);
-        let memory_address = self
-            .dev_info
-            .as_ref()
-            .map(|d| d.memory_address)
-            .expect("Dev info not initialized");
-
-        for (area_img_info, buffer) in area_iter {
-            self.load_image_area(
-                memory_address,
-                MemoryConverterSetting {
-                    rotation: (&self.config.rotation).into(),
-                    ..Default::default()
-                },
-                &area_img_info,
-                buffer,
-            )?;
+        if self.config.load_alignment {
+            return self.load_aligned(memory_address, &area, core::iter::repeat(color));
         }
 
+        let area = self.load_flip()?.rect(&area);
+        let a = AreaSerializer::new(area, color, self.config.max_buffer_size)?;
+
+        self.with_staging(|it8951, staging| {
+            for (area_img_info, buffer) in AreaSerializerIterator::new(&a, staging) {
+                it8951.load_image_area(
+                    memory_address,
+                    MemoryConverterSetting {
+                        rotation: it8951.load_rotation(),
+                        ..Default::default()
+                    },
+                    &area_img_info,
+                    buffer,
+                )?;
+            }
+            Ok(())
+        })?;
+
         #[cfg(feature = "defmt")]
         defmt::trace!("Embedded graphics: Fill solid");
 
         Ok(())
     }
 
-    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    /// Writes the colors of a area to the image buffer at memory_address
+    fn fill_contiguous_at<I>(
+        &mut self,
+        memory_address: u32,
+        area: &Rectangle,
+        colors: I,
+    ) -> Result<(), Error>
     where
-        I: IntoIterator<Item = Self::Color>,
+        I: IntoIterator<Item = Gray4>,
+    {
+        if self.config.load_alignment {
+            return self.load_aligned(memory_address, area, colors);
+        }
+        self.write_contiguous_at(memory_address, area, colors)
+    }
+
+    /// Writes the colors of a area to the image buffer at memory_address without alignment
+    fn write_contiguous_at<I>(
+        &mut self,
+        memory_address: u32,
+        area: &Rectangle,
+        colors: I,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Gray4>,
     {
         let bb = self.bounding_box();
         let iter = convert_color_to_pixel_iterator(area, &bb, colors.into_iter());
-        let memory_address = self
-            .dev_info
-            .as_ref()
-            .map(|d| d.memory_address)
-            .expect("Dev info not initialized");
 
-        let pixel = PixelSerializer::<_, TOrigin>::new(
+        let mut pixel = PixelSerializer::new(
             area.intersection(&bb),
             iter,
             self.config.max_buffer_size,
-        );
+            self.load_flip()?,
+        )?;
 
-        for (area_img_info, buffer) in pixel {
-            self.load_image_area(
-                memory_address,
-                MemoryConverterSetting {
-                    endianness: memory_converter_settings::MemoryConverterEndianness::LittleEndian,
-                    rotation: (&self.config.rotation).into(),
-                    ..Default::default()
-                },
-                &area_img_info,
-                &buffer,
-            )?;
-        }
+        self.with_staging(|it8951, staging| {
+            while let Some((area_img_info, buffer)) = pixel.next_into(staging) {
+                it8951.load_image_area(
+                    memory_address,
+                    MemoryConverterSetting {
+                        endianness:
+                            memory_converter_settings::MemoryConverterEndianness::LittleEndian,
+                        rotation: it8951.load_rotation(),
+                        ..Default::default()
+                    },
+                    &area_img_info,
+                    buffer,
+                )?;
+            }
+            Ok(())
+        })?;
 
         #[cfg(feature = "defmt")]
         defmt::trace!("Embedded graphics: Fill contiguous");
@@ -812,27 +1636,31 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin> DrawTarget
         Ok(())
     }
 
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    /// Writes single pixels to the image buffer at memory_address
+    fn draw_iter_at<I>(&mut self, memory_address: u32, pixels: I) -> Result<(), Error>
     where
-        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+        I: IntoIterator<Item = Pixel<Gray4>>,
     {
-        let memory_address = self
-            .dev_info
-            .as_ref()
-            .map(|d| d.memory_address)
-            .expect("Dev info not initialized");
         let size = self.size();
         let width = size.width as i32;
         let height = size.height as i32;
+        let flip = self.load_flip()?;
         for Pixel(coord, color) in pixels.into_iter() {
             if (coord.x >= 0 && coord.x < width) && (coord.y >= 0 && coord.y < height) {
+                if self.config.load_alignment {
+                    let area = Rectangle::new(coord, Size::new(1, 1));
+                    self.load_aligned(memory_address, &area, core::iter::once(color))?;
+                    continue;
+                }
+
+                let coord = flip.point(coord);
                 let raw_color = color.luma();
                 let data = [raw_color << 4 | raw_color, raw_color << 4 | raw_color];
 
                 self.load_image_area(
                     memory_address,
                     MemoryConverterSetting {
-                        rotation: (&self.config.rotation).into(),
+                        rotation: self.load_rotation(),
                         ..Default::default()
                     },
                     &AreaImgInfo {
@@ -853,29 +1681,123 @@ impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin> DrawTarget
     }
 }
 
+impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin> DrawTarget
+    for IT8951<IT8951Interface, TOrigin, Run>
+{
+    type Color = Gray4;
+
+    type Error = Error;
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let size = self.size();
+
+        self.fill_solid(
+            &Rectangle::new(
+                Point::zero(),
+                Size {
+                    width: size.width,
+                    height: size.height,
+                },
+            ),
+            color,
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.add_dirty(
+            area,
+            AreaContent {
+                levels: 1 << color.luma(),
+            },
+        );
+        let memory_address = self.default_memory_address()?;
+        self.fill_solid_at(memory_address, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let mut content = AreaContent::default();
+        let memory_address = self.default_memory_address()?;
+        let result = self.fill_contiguous_at(
+            memory_address,
+            area,
+            colors
+                .into_iter()
+                .inspect(|color| content.levels |= 1 << color.luma()),
+        );
+        self.add_dirty(area, content);
+        result
+    }
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        // track the bounding box and gray levels of all drawn pixels
+        let bounding_box = self.bounding_box();
+        let mut drawn: Option<Rectangle> = None;
+        let mut content = AreaContent::default();
+        let memory_address = self.default_memory_address()?;
+        let result = self.draw_iter_at(
+            memory_address,
+            pixels.into_iter().inspect(|Pixel(point, color)| {
+                if !bounding_box.contains(*point) {
+                    return;
+                }
+                content.levels |= 1 << color.luma();
+                let pixel = Rectangle::new(*point, Size::new(1, 1));
+                drawn = Some(match &drawn {
+                    Some(drawn) => dirty::union(drawn, &pixel),
+                    None => pixel,
+                });
+            }),
+        );
+        if let Some(drawn) = drawn {
+            self.add_dirty(&drawn, content);
+        }
+        result
+    }
+}
+
 impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin> OriginDimensions
     for IT8951<IT8951Interface, TOrigin, Run>
 {
+    /// Zero sized if the driver is not initialized
     fn size(&self) -> Size {
-        let dev_info = self.dev_info.as_ref().unwrap();
-        let (w, h) = (dev_info.panel_width as u32, dev_info.panel_height as u32);
-        let (w, h) = match self.config.rotation {
-            Rotation::Rotate0 | Rotation::Rotate180 => (w, h),
-            Rotation::Rotate90 | Rotation::Rotate270 => (h, w),
-        };
-        Size::new(w, h)
+        self.panel_size()
+            .map(|panel| self.panel_orientation().display_size(panel))
+            .unwrap_or_default()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloc::vec::Vec;
+    use alloc::{
+        collections::{BTreeMap, VecDeque},
+        vec::Vec,
+    };
 
     // Mock interface for testing
     struct MockInterface {
         commands: Vec<(u16, Vec<u16>)>,
         timeout: core::time::Duration,
+        // sparse controller memory, accessed by memory bursts
+        memory: BTreeMap<u32, u8>,
+        // values returned by read_data, 0 if empty
+        read_data: VecDeque<u16>,
+        // LUT version reported in the dev info
+        lut_version: &'static str,
+        // firmware version reported in the dev info
+        firmware_version: &'static str,
+        // register values written by REG_WR, returned if read_data is empty
+        registers: BTreeMap<u16, u16>,
+        // registers ignoring REG_WR
+        read_only: Vec<u16>,
+        // number of memory burst reads before the next one fails, never fails if None
+        burst_reads_until_error: Option<usize>,
     }
 
     impl MockInterface {
@@ -883,8 +1805,26 @@ mod tests {
             MockInterface {
                 commands: Vec::new(),
                 timeout: core::time::Duration::from_secs(1),
+                memory: BTreeMap::new(),
+                read_data: VecDeque::new(),
+                lut_version: "",
+                firmware_version: "",
+                registers: BTreeMap::new(),
+                read_only: Vec::new(),
+                burst_reads_until_error: None,
             }
         }
+
+        // address of the last started memory burst
+        fn burst_address(&self, burst_cmd: u16) -> u32 {
+            let (_, args) = self
+                .commands
+                .iter()
+                .rev()
+                .find(|(cmd, _)| *cmd == burst_cmd)
+                .expect("No memory burst started");
+            args[0] as u32 | (args[1] as u32) << 16
+        }
     }
 
     impl interface::IT8951Interface for MockInterface {
@@ -896,11 +1836,28 @@ mod tests {
             Ok(())
         }
 
-        fn write_data(&mut self, _data: u16) -> Result<(), interface::Error> {
+        fn write_data(&mut self, data: u16) -> Result<(), interface::Error> {
+            // data is recorded as argument of the last issued command
+            if let Some((cmd, args)) = self.commands.last_mut() {
+                args.push(data);
+                if *cmd == command::IT8951_TCON_REG_WR
+                    && args.len() == 2
+                    && !self.read_only.contains(&args[0])
+                {
+                    self.registers.insert(args[0], args[1]);
+                }
+            }
             Ok(())
         }
 
-        fn write_multi_data(&mut self, _data: &[u8]) -> Result<(), interface::Error> {
+        fn write_multi_data(&mut self, data: &[u8]) -> Result<(), interface::Error> {
+            if self.commands.last().map(|(cmd, _)| *cmd) == Some(command::IT8951_TCON_MEM_BST_WR) {
+                // data is transferred as big endian u16 words
+                let addr = self.burst_address(command::IT8951_TCON_MEM_BST_WR);
+                for (i, byte) in data.iter().enumerate() {
+                    self.memory.insert(addr + (i ^ 1) as u32, *byte);
+                }
+            }
             Ok(())
         }
 
@@ -919,23 +1876,55 @@ mod tests {
         }
 
         fn read_data(&mut self) -> Result<u16, interface::Error> {
-            Ok(0)
+            if let Some(data) = self.read_data.pop_front() {
+                return Ok(data);
+            }
+            match self.commands.last() {
+                Some((command::IT8951_TCON_REG_RD, args)) => {
+                    Ok(*self.registers.get(&args[0]).unwrap_or(&0))
+                }
+                _ => Ok(0),
+            }
         }
 
         fn read_multi_data(&mut self, buf: &mut [u8]) -> Result<(), interface::Error> {
+            if self.commands.last().map(|(cmd, _)| *cmd) == Some(command::IT8951_TCON_MEM_BST_RD_S)
+            {
+                if let Some(remaining) = self.burst_reads_until_error.as_mut() {
+                    if *remaining == 0 {
+                        return Err(interface::Error::SpiError);
+                    }
+                    *remaining -= 1;
+                }
+                // data is transferred as big endian u16 words
+                let addr = self.burst_address(command::IT8951_TCON_MEM_BST_RD_T);
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    *byte = *self.memory.get(&(addr + (i ^ 1) as u32)).unwrap_or(&0);
+                }
+                return Ok(());
+            }
             // Fill with mock data for dev info
             if buf.len() >= 40 {
+                // u16 words are swapped by convert_endianness
                 // panel_width = 1872 (0x0750)
-                buf[0] = 0x50;
-                buf[1] = 0x07;
+                buf[0] = 0x07;
+                buf[1] = 0x50;
                 // panel_height = 1404 (0x057C)
-                buf[2] = 0x7C;
-                buf[3] = 0x05;
+                buf[2] = 0x05;
+                buf[3] = 0x7C;
                 // memory_address = 0x00001000
-                buf[4] = 0x00;
-                buf[5] = 0x10;
+                buf[4] = 0x10;
+                buf[5] = 0x00;
                 buf[6] = 0x00;
                 buf[7] = 0x00;
+                // firmware_version
+                for (i, byte) in self.firmware_version.bytes().enumerate() {
+                    buf[(8 + i) ^ 1] = byte;
+                }
+                // lut_version
+                for (i, byte) in self.lut_version.bytes().enumerate() {
+                    buf[(25 + i) ^ 1] = byte;
+                }
             }
             Ok(())
         }
@@ -969,7 +1958,7 @@ mod tests {
             area_h: 75,
         };
 
-        let rotated = driver.rotate_area_info(&area);
+        let rotated = driver.panel_area_info(&area).unwrap();
 
         assert_eq!(rotated.area_x, 100);
         assert_eq!(rotated.area_y, 200);
@@ -980,8 +1969,10 @@ mod tests {
     #[test]
     fn test_rotate_area_info_rotate90() {
         let mock = MockInterface::new();
-        let mut config = Config::default();
-        config.rotation = Rotation::Rotate90;
+        let config = Config {
+            rotation: Rotation::Rotate90,
+            ..Default::default()
+        };
         let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
         let mut driver = driver.into_state::<Run>();
         driver.dev_info = Some(DevInfo {
@@ -999,7 +1990,7 @@ mod tests {
             area_h: 75,
         };
 
-        let rotated = driver.rotate_area_info(&area);
+        let rotated = driver.panel_area_info(&area).unwrap();
 
         // Rotate90: (y, ph - w - x, h, w)
         assert_eq!(rotated.area_x, 200);
@@ -1011,8 +2002,10 @@ mod tests {
     #[test]
     fn test_rotate_area_info_rotate180() {
         let mock = MockInterface::new();
-        let mut config = Config::default();
-        config.rotation = Rotation::Rotate180;
+        let config = Config {
+            rotation: Rotation::Rotate180,
+            ..Default::default()
+        };
         let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
         let mut driver = driver.into_state::<Run>();
         driver.dev_info = Some(DevInfo {
@@ -1030,7 +2023,7 @@ mod tests {
             area_h: 75,
         };
 
-        let rotated = driver.rotate_area_info(&area);
+        let rotated = driver.panel_area_info(&area).unwrap();
 
         // Rotate180: (pw - w - x, ph - h - y, w, h)
         assert_eq!(rotated.area_x, 1872 - 50 - 100);
@@ -1042,8 +2035,10 @@ mod tests {
     #[test]
     fn test_rotate_area_info_rotate270() {
         let mock = MockInterface::new();
-        let mut config = Config::default();
-        config.rotation = Rotation::Rotate270;
+        let config = Config {
+            rotation: Rotation::Rotate270,
+            ..Default::default()
+        };
         let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
         let mut driver = driver.into_state::<Run>();
         driver.dev_info = Some(DevInfo {
@@ -1061,7 +2056,7 @@ mod tests {
             area_h: 75,
         };
 
-        let rotated = driver.rotate_area_info(&area);
+        let rotated = driver.panel_area_info(&area).unwrap();
 
         // Rotate270: (pw - h - y, x, h, w)
         assert_eq!(rotated.area_x, 1872 - 75 - 200);
@@ -1075,8 +2070,10 @@ mod tests {
         // This test verifies that load_image_area does NOT apply coordinate transformation
         // even when rotation is enabled. The transformation should only happen in display_area.
         let mock = MockInterface::new();
-        let mut config = Config::default();
-        config.rotation = Rotation::Rotate180; // Enable rotation
+        let config = Config {
+            rotation: Rotation::Rotate180,
+            ..Default::default()
+        }; // Enable rotation
         let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
         let mut driver = driver.into_state::<Run>();
         driver.dev_info = Some(DevInfo {
@@ -1172,8 +2169,10 @@ mod tests {
     #[test]
     fn test_display_area_applies_rotation_once() {
         let mock = MockInterface::new();
-        let mut config = Config::default();
-        config.rotation = Rotation::Rotate180;
+        let config = Config {
+            rotation: Rotation::Rotate180,
+            ..Default::default()
+        };
         let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
         let mut driver = driver.into_state::<Run>();
         driver.dev_info = Some(DevInfo {
@@ -1274,4 +2273,1857 @@ mod tests {
         };
         assert!(driver.display_area(&area, WaveformMode::GL16).is_ok());
     }
+
+    #[test]
+    fn test_hardware_fill() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = AreaImgInfo {
+            area_x: 10,
+            area_y: 20,
+            area_w: 30,
+            area_h: 40,
+        };
+        assert!(driver
+            .hardware_fill(&area, Gray4::new(0x5), WaveformMode::GL16)
+            .is_ok());
+        // the image buffer is not changed
+        assert!(driver.dirty_regions().is_empty());
+
+        let commands = &driver.interface.commands;
+        // no pixel data is uploaded
+        assert!(!commands
+            .iter()
+            .any(|(cmd, _)| *cmd == command::IT8951_TCON_LD_IMG_AREA));
+        // fill value is set and the fill flag enabled before the update
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::IT8951_TCON_REG_WR && args[..] == [register::LUT0ABFRV, 0x55]
+        }));
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::IT8951_TCON_REG_WR
+                && args[..] == [register::UP1SR + 2, register::UP1SR_FILL_EN]
+        }));
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::USDEF_I80_CMD_DPY_AREA && args[..] == [10, 20, 30, 40, 3]
+        }));
+        // fill flag is cleared afterwards
+        assert_eq!(
+            commands.last(),
+            Some(&(
+                command::IT8951_TCON_REG_WR,
+                vec![register::UP1SR + 2, 0x0000]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_fill_solid_serializes_pixels() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = Rectangle::new(Point::new(10, 20), Size::new(30, 40));
+        assert!(driver.fill_solid(&area, Gray4::new(0x5)).is_ok());
+
+        let commands = &driver.interface.commands;
+        assert!(commands
+            .iter()
+            .any(|(cmd, _)| *cmd == command::IT8951_TCON_LD_IMG_AREA));
+        assert!(!commands
+            .iter()
+            .any(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA));
+    }
+
+    #[test]
+    fn test_display_dirty() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = Rectangle::new(Point::new(10, 20), Size::new(30, 40));
+        assert!(driver.fill_solid(&area, Gray4::new(0x5)).is_ok());
+        assert!(driver
+            .draw_iter([
+                Pixel(Point::new(1000, 1000), Gray4::WHITE),
+                Pixel(Point::new(1001, 1003), Gray4::WHITE),
+                // outside of the display
+                Pixel(Point::new(5000, 5000), Gray4::WHITE),
+            ])
+            .is_ok());
+        assert_eq!(driver.dirty_regions().len(), 2);
+
+        driver.interface.commands.clear();
+        assert!(driver.display_dirty(WaveformMode::DirectUpdate).is_ok());
+        assert!(driver.dirty_regions().is_empty());
+
+        let mut refreshed: Vec<Vec<u16>> = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .map(|(_, args)| args.clone())
+            .collect();
+        refreshed.sort();
+        assert_eq!(
+            refreshed,
+            vec![
+                vec![10, 20, 30, 40, WaveformMode::DirectUpdate as u16],
+                vec![1000, 1000, 2, 4, WaveformMode::DirectUpdate as u16],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_dirty_auto() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let text = Rectangle::new(Point::new(0, 0), Size::new(100, 20));
+        assert!(driver.fill_solid(&text, Gray4::WHITE).is_ok());
+        assert!(driver
+            .draw_iter([Pixel(Point::new(10, 10), Gray4::BLACK)])
+            .is_ok());
+        let image = Rectangle::new(Point::new(1000, 1000), Size::new(2, 2));
+        assert!(driver
+            .fill_contiguous(&image, [0x0, 0x5, 0x7, 0xF].map(Gray4::new))
+            .is_ok());
+
+        driver.interface.commands.clear();
+        assert!(driver.display_dirty(UpdateMode::Auto).is_ok());
+
+        let mut refreshed: Vec<Vec<u16>> = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .map(|(_, args)| args.clone())
+            .collect();
+        refreshed.sort();
+        assert_eq!(
+            refreshed,
+            vec![
+                vec![0, 0, 100, 20, WaveformMode::DirectUpdate as u16],
+                vec![1000, 1000, 2, 2, WaveformMode::GrayscaleClearing16 as u16],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ghosting_budget_promotes_update() {
+        let mock = MockInterface::new();
+        let config = Config {
+            rotation: Rotation::Rotate90,
+            ghosting: Some(ghosting::GhostingConfig {
+                budget: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = AreaImgInfo {
+            area_x: 0,
+            area_y: 0,
+            area_w: 10,
+            area_h: 10,
+        };
+        assert!(driver.display_area(&area, WaveformMode::A2).is_ok());
+        assert!(driver.display_area(&area, WaveformMode::A2).is_ok());
+
+        let modes: Vec<u16> = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .map(|(_, args)| args[4])
+            .collect();
+        assert_eq!(
+            modes,
+            vec![
+                WaveformMode::A2 as u16,
+                WaveformMode::GrayscaleClearing16 as u16
+            ]
+        );
+
+        // counters use panel coordinates
+        let budget = driver.ghosting_budget().unwrap();
+        assert_eq!(budget.max_counter(), 0);
+        assert!(driver.display_area(&area, WaveformMode::A2).is_ok());
+        assert_eq!(driver.ghosting_budget().unwrap().counter(0, 1403), 1);
+
+        driver.reset_ghosting_budget();
+        assert_eq!(driver.ghosting_budget().unwrap().max_counter(), 0);
+    }
+
+    #[test]
+    fn test_display_area_buf_blended() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = AreaImgInfo {
+            area_x: 100,
+            area_y: 200,
+            area_w: 50,
+            area_h: 75,
+        };
+        assert!(driver
+            .display_area_buf_blended(&area, WaveformMode::GL16, 0x00400000, 0x80)
+            .is_ok());
+
+        let commands = &driver.interface.commands;
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::IT8951_TCON_REG_WR && args[..] == [register::LUT0ABFRV, 0x8000]
+        }));
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::IT8951_TCON_REG_WR
+                && args[..] == [register::UP1SR + 2, register::UP1SR_ALPHA_BLEND_EN]
+        }));
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::USDEF_I80_CMD_DPY_BUF_AREA
+                && args[..] == [100, 200, 50, 75, 3, 0x0000, 0x0040]
+        }));
+        // blend flag is cleared afterwards
+        assert_eq!(
+            commands.last(),
+            Some(&(
+                command::IT8951_TCON_REG_WR,
+                vec![register::UP1SR + 2, 0x0000]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_alloc_image_buffer_after_default_buffer() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let buffer = driver.alloc_image_buffer(1872, 1404, 8).unwrap();
+        assert_eq!(buffer.addr, 0x001236E0 + 1872 * 1404);
+        assert!(!buffer.overlaps(&driver.image_buffer().unwrap()));
+
+        // the default image buffer is never handed out
+        assert_eq!(
+            driver.reserve_image_buffer(driver.image_buffer().unwrap()),
+            Err(Error::OutOfMemory)
+        );
+    }
+
+    #[test]
+    fn test_offscreen_surface_targets_buffer() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let small = driver.alloc_image_buffer(100, 100, 8).unwrap();
+        assert!(offscreen::OffscreenSurface::new(&mut driver, small).is_err());
+
+        let buffer = driver.alloc_image_buffer(1872, 1404, 8).unwrap();
+        let mut surface = offscreen::OffscreenSurface::new(&mut driver, buffer).unwrap();
+        surface
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(4, 1)),
+                Gray4::BLACK,
+            )
+            .unwrap();
+        let area = AreaImgInfo {
+            area_x: 0,
+            area_y: 0,
+            area_w: 4,
+            area_h: 1,
+        };
+        surface.present(&area, WaveformMode::DU4).unwrap();
+
+        let commands = &driver.interface.commands;
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::IT8951_TCON_REG_WR
+                && args[..] == [register::LISAR + 2, (buffer.addr >> 16) as u16]
+        }));
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::IT8951_TCON_REG_WR && args[..] == [register::LISAR, buffer.addr as u16]
+        }));
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::USDEF_I80_CMD_DPY_BUF_AREA
+                && args[..]
+                    == [
+                        0,
+                        0,
+                        4,
+                        1,
+                        7,
+                        buffer.addr as u16,
+                        (buffer.addr >> 16) as u16,
+                    ]
+        }));
+    }
+
+    #[test]
+    fn test_copy_area_unaligned_4bpp() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let src = driver.alloc_image_buffer(8, 2, 4).unwrap();
+        let dst = driver.alloc_image_buffer(8, 2, 4).unwrap();
+        // pixel values 0x1..0x8 in the first row of src
+        driver
+            .memory_burst_write(src.addr, &mut [0x21, 0x43, 0x65, 0x87])
+            .unwrap();
+        driver
+            .memory_burst_write(dst.addr, &mut [0xFF, 0xFF, 0xFF, 0xFF])
+            .unwrap();
+
+        // pixels 1..4 of src to pixel 3 of the dst
+        driver
+            .copy_area(
+                &src,
+                &Rectangle::new(Point::new(1, 0), Size::new(3, 1)),
+                &dst,
+                Point::new(3, 0),
+            )
+            .unwrap();
+
+        let mut data = [0x00; 4];
+        driver.memory_burst_read(dst.addr, &mut data).unwrap();
+        assert_eq!(data, [0xFF, 0x2F, 0x43, 0xFF]);
+    }
+
+    #[test]
+    fn test_copy_area_overlapping_in_chunks() {
+        let mock = MockInterface::new();
+        let config = Config {
+            max_buffer_size: 8,
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let buffer = driver.alloc_image_buffer(16, 3, 8).unwrap();
+        let mut content: Vec<u8> = (0..48).collect();
+        driver
+            .memory_burst_write(buffer.addr, &mut content)
+            .unwrap();
+
+        // scroll the top left 10x2 pixels one row down and three pixels right
+        driver
+            .copy_area(
+                &buffer,
+                &Rectangle::new(Point::new(0, 0), Size::new(10, 2)),
+                &buffer,
+                Point::new(3, 1),
+            )
+            .unwrap();
+
+        let mut data = [0x00; 48];
+        driver.memory_burst_read(buffer.addr, &mut data).unwrap();
+        let mut expected: Vec<u8> = (0..48).collect();
+        for row in 0..2 {
+            for x in 0..10 {
+                expected[(row + 1) * 16 + x + 3] = (row * 16 + x) as u8;
+            }
+        }
+        assert_eq!(data[..], expected[..]);
+    }
+
+    #[test]
+    fn test_copy_area_checks_buffers() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+
+        let buffer = ImageBuffer {
+            addr: 0x001236E0,
+            width: 16,
+            height: 4,
+            bpp: 8,
+        };
+        let rect = Rectangle::new(Point::zero(), Size::new(4, 4));
+        for invalid in [
+            ImageBuffer { bpp: 0, ..buffer },
+            ImageBuffer { bpp: 16, ..buffer },
+            ImageBuffer { width: 0, ..buffer },
+        ] {
+            assert_eq!(
+                driver.copy_area(&invalid, &rect, &invalid, Point::zero()),
+                Err(Error::InvalidImageBuffer)
+            );
+        }
+        let overflowing = ImageBuffer {
+            addr: u32::MAX - 10,
+            ..buffer
+        };
+        assert_eq!(
+            driver.copy_area(&buffer, &rect, &overflowing, Point::zero()),
+            Err(Error::OutOfMemory)
+        );
+        let far = Rectangle::new(Point::new(i32::MIN, 0), Size::new(4, 4));
+        assert_eq!(
+            driver.copy_area(&buffer, &far, &buffer, Point::new(i32::MAX, 0)),
+            Err(Error::InvalidArea)
+        );
+        assert!(driver.interface.commands.is_empty());
+    }
+
+    #[test]
+    fn test_double_buffered_flip() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 16,
+            panel_height: 8,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let mut epd = driver.into_double_buffered(true).unwrap();
+        let front = epd.front_buffer();
+        let back = epd.back_buffer();
+        assert_eq!(front.addr, 0x001236E0);
+        assert!(!front.overlaps(&back));
+
+        epd.fill_solid(
+            &Rectangle::new(Point::new(4, 2), Size::new(4, 2)),
+            Gray4::BLACK,
+        )
+        .unwrap();
+        // the mock memory does not run the image load engine,
+        // the drawn area of the buffer to display is seeded with a pattern instead
+        let rows = [[0x11, 0x22, 0x33, 0x44], [0x55, 0x66, 0x77, 0x88]];
+        for (y, row) in rows.iter().enumerate() {
+            epd.driver()
+                .memory_burst_write(back.addr + (2 + y as u32) * 16 + 4, &mut row.clone())
+                .unwrap();
+        }
+        epd.flip(WaveformMode::GL16).unwrap();
+        assert_eq!(epd.front_buffer(), back);
+        assert_eq!(epd.back_buffer(), front);
+
+        // the drawn area is copied forward from the displayed buffer
+        for (y, row) in rows.iter().enumerate() {
+            let mut data = [0x00; 4];
+            epd.driver()
+                .memory_burst_read(front.addr + (2 + y as u32) * 16 + 4, &mut data)
+                .unwrap();
+            assert_eq!(data, *row);
+        }
+
+        let commands = &epd.driver().interface.commands;
+        assert!(commands.iter().any(|(cmd, args)| {
+            *cmd == command::USDEF_I80_CMD_DPY_BUF_AREA
+                && args[..] == [0, 0, 16, 8, 3, back.addr as u16, (back.addr >> 16) as u16]
+        }));
+        let last_write = commands
+            .iter()
+            .rev()
+            .find(|(cmd, _)| *cmd == command::IT8951_TCON_MEM_BST_WR)
+            .unwrap();
+        assert_eq!(
+            last_write.1[..],
+            [
+                (front.addr + 3 * 16 + 4) as u16,
+                (front.addr >> 16) as u16,
+                4,
+                0
+            ]
+        );
+
+        let driver = epd.into_inner().unwrap();
+        assert!(driver
+            .allocator
+            .as_ref()
+            .is_some_and(|a| a.allocated().is_empty()));
+    }
+
+    #[test]
+    fn test_read_area_rotated() {
+        let mock = MockInterface::new();
+        let config = Config {
+            rotation: Rotation::Rotate90,
+            max_buffer_size: 4,
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 8,
+            panel_height: 4,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        // 8bpp panel content, value is 0x10 * x + y
+        let mut content: Vec<u8> = (0..32).map(|i| (((i % 8) << 4) | (i / 8)) as u8).collect();
+        driver.memory_burst_write(0x001236E0, &mut content).unwrap();
+
+        // display is 4x8, display x maps to panel y reversed, display y to panel x
+        let pixels = driver
+            .read_area(&Rectangle::new(Point::new(1, 3), Size::new(2, 3)))
+            .unwrap();
+        let luma: Vec<u8> = pixels.iter().map(|p| p.luma()).collect();
+        assert_eq!(luma, [3, 3, 4, 4, 5, 5]);
+    }
+
+    #[test]
+    fn test_screenshot() {
+        let mock = MockInterface::new();
+        let config = Config {
+            max_buffer_size: 4,
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 6,
+            panel_height: 2,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let mut content: Vec<u8> = (0..12).collect();
+        driver.memory_burst_write(0x001236E0, &mut content).unwrap();
+
+        let mut small = [0x00; 11];
+        assert_eq!(driver.screenshot(&mut small), Err(Error::BufferTooSmall));
+
+        let mut buf = [0x00; 12];
+        driver.screenshot(&mut buf).unwrap();
+        assert_eq!(buf[..], (0..12).collect::<Vec<u8>>()[..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_screenshot_pgm() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 2,
+            panel_height: 1,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+        driver
+            .memory_burst_write(0x001236E0, &mut [0xF0, 0x80])
+            .unwrap();
+
+        let mut pgm = Vec::new();
+        driver.screenshot_pgm(&mut pgm).unwrap();
+        assert_eq!(pgm, b"P5\n2 1\n255\n\xF0\x80");
+    }
+
+    #[test]
+    fn test_temperature() {
+        let mut mock = MockInterface::new();
+        // measured -5°C, forced 0°C and after forcing -10°C
+        mock.read_data
+            .extend([(-5i16) as u16, 0, (-5i16) as u16, (-10i16) as u16]);
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+
+        assert_eq!(driver.get_temperature(), Ok(-5));
+        assert!(driver.set_forced_temperature(Some(-10)).is_ok());
+        // the measured temperature is forced instead
+        assert!(driver.set_forced_temperature(None).is_ok());
+
+        let commands = &driver.interface.commands;
+        assert_eq!(
+            commands[0],
+            (command::USDEF_I80_CMD_TEMP, vec![command::TEMP_GET])
+        );
+        assert_eq!(
+            commands[1],
+            (
+                command::USDEF_I80_CMD_TEMP,
+                vec![command::TEMP_SET_FORCED, 0xFFF6]
+            )
+        );
+        assert_eq!(
+            commands[2],
+            (command::USDEF_I80_CMD_TEMP, vec![command::TEMP_GET])
+        );
+        assert_eq!(
+            commands[3],
+            (
+                command::USDEF_I80_CMD_TEMP,
+                vec![command::TEMP_SET_FORCED, 0xFFFB]
+            )
+        );
+    }
+
+    #[test]
+    fn test_start_display_area() {
+        let mut mock = MockInterface::new();
+        // engine idle before the refresh, busy on the first poll
+        mock.read_data.extend([0, 1, 0]);
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = AreaImgInfo {
+            area_x: 0,
+            area_y: 0,
+            area_w: 10,
+            area_h: 10,
+        };
+        let mut handle = driver
+            .start_display_area(&area, WaveformMode::GrayscaleClearing16)
+            .unwrap();
+        assert_eq!(handle.is_done(), Ok(false));
+        assert_eq!(handle.is_done(), Ok(true));
+        assert!(handle.wait().is_ok());
+        assert_eq!(driver.is_display_ready(), Ok(true));
+        assert!(driver
+            .interface
+            .commands
+            .iter()
+            .any(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA));
+    }
+
+    #[test]
+    fn test_display_engine_timeout_uses_backoff() {
+        let mut mock = MockInterface::new();
+        // display engine stays busy
+        mock.read_data.extend([1; 10]);
+        let config = Config {
+            timeout_display_engine: core::time::Duration::from_millis(3),
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+
+        assert_eq!(
+            driver.wait_for_display_ready(),
+            Err(Error::DisplayEngineTimeout)
+        );
+        // 200 + 400 + 800 + 1000 + 1000us, polled before every delay and once after
+        assert_eq!(driver.interface.read_data.len(), 4);
+    }
+
+    #[test]
+    fn test_parallel_updates_on_lut_engines() {
+        let mut mock = MockInterface::new();
+        // LUTAFSR before and after every update, and while waiting for the display engine
+        mock.read_data
+            .extend([0, 0b01, 0b01, 0b11, 0b11, 0b11, 0, 0b01, 0b11, 0, 0b01]);
+        let config = Config {
+            lut_engines: 2,
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = |x, y| AreaImgInfo {
+            area_x: x,
+            area_y: y,
+            area_w: 100,
+            area_h: 100,
+        };
+        // engine idle
+        driver.display_area(&area(0, 0), WaveformMode::A2).unwrap();
+        // one engine busy, no overlap
+        driver
+            .display_area(&area(500, 0), WaveformMode::A2)
+            .unwrap();
+        assert_eq!(driver.interface.read_data.len(), 7);
+        // overlaps the first update, waits until the engine is idle
+        driver
+            .display_area(&area(50, 50), WaveformMode::A2)
+            .unwrap();
+        assert_eq!(driver.interface.read_data.len(), 3);
+        // no overlap, but all engines busy
+        driver
+            .display_area(&area(1000, 0), WaveformMode::A2)
+            .unwrap();
+        assert!(driver.interface.read_data.is_empty());
+
+        let refreshes = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .count();
+        assert_eq!(refreshes, 4);
+    }
+
+    #[test]
+    fn test_load_alignment_widens_loads() {
+        for (rotation, expected) in [
+            (Rotation::Rotate0, [4, 1, 4, 1]),
+            (Rotation::Rotate90, [5, 0, 1, 4]),
+        ] {
+            let mock = MockInterface::new();
+            let config = Config {
+                rotation,
+                load_alignment: true,
+                ..Default::default()
+            };
+            let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+            let mut driver = driver.into_state::<Run>();
+            driver.dev_info = Some(DevInfo {
+                panel_width: 16,
+                panel_height: 8,
+                memory_address: 0x001236E0,
+                firmware_version: String::from("test"),
+                lut_version: String::from("test"),
+            });
+
+            driver
+                .fill_solid(
+                    &Rectangle::new(Point::new(5, 1), Size::new(1, 1)),
+                    Gray4::WHITE,
+                )
+                .unwrap();
+
+            let commands = &driver.interface.commands;
+            // the padding pixels are read back
+            assert!(commands
+                .iter()
+                .any(|(cmd, _)| *cmd == command::IT8951_TCON_MEM_BST_RD_T));
+            let (_, args) = commands
+                .iter()
+                .find(|(cmd, _)| *cmd == command::IT8951_TCON_LD_IMG_AREA)
+                .unwrap();
+            assert_eq!(args[1..], expected);
+        }
+    }
+
+    #[test]
+    fn test_a2_display_area_is_aligned() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = AreaImgInfo {
+            area_x: 33,
+            area_y: 7,
+            area_w: 10,
+            area_h: 5,
+        };
+        driver.display_area(&area, WaveformMode::A2).unwrap();
+        driver.display_area(&area, WaveformMode::GL16).unwrap();
+
+        let args: Vec<Vec<u16>> = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .map(|(_, args)| args[..4].to_vec())
+            .collect();
+        assert_eq!(args, vec![vec![32, 7, 32, 5], vec![33, 7, 10, 5]]);
+    }
+
+    #[test]
+    fn test_set_vcom_verifies_value() {
+        let mut mock = MockInterface::new();
+        mock.read_data.extend([1530, 1500]);
+        let config = Config {
+            verify_vcom: true,
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+
+        let vcom = Vcom::from_volts(-1.53).unwrap();
+        assert_eq!(driver.set_vcom(vcom), Ok(()));
+        assert_eq!(driver.set_vcom(vcom), Err(Error::VcomMismatch));
+        assert_eq!(
+            driver.interface.commands[0],
+            (command::USDEF_I80_CMD_VCOM, vec![0x0001, 1530])
+        );
+    }
+
+    #[test]
+    fn test_new_with_profile() {
+        let mock = MockInterface::new();
+        let config = Config {
+            rotation: Rotation::Rotate90,
+            ..Default::default()
+        };
+        let driver = IT8951::new_with_profile(mock, config, &profiles::WAVESHARE_9_7INCH);
+        let driver = driver.init_no_vcom().unwrap();
+
+        assert_eq!(driver.panel_mirror, Mirror::None);
+        assert_eq!(driver.config.rotation, Rotation::Rotate90);
+        assert_eq!(driver.profile().unwrap().name, "Waveshare 9.7inch");
+        assert!(driver
+            .interface
+            .commands
+            .contains(&(command::IT8951_TCON_REG_WR, vec![0x0038, 0x0602])));
+
+        let mock = MockInterface::new();
+        let driver =
+            IT8951::new_with_profile(mock, Config::default(), &profiles::WAVESHARE_10_3INCH);
+        let driver = driver.init_no_vcom().unwrap();
+
+        assert_eq!(driver.panel_mirror, Mirror::Horizontal);
+        assert!(!driver
+            .interface
+            .commands
+            .contains(&(command::IT8951_TCON_REG_WR, vec![0x0038, 0x0602])));
+    }
+
+    #[test]
+    fn test_init_detect() {
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M841_TFA5210";
+        mock.read_data.extend([1530]);
+        let driver = IT8951::new_with_origin(mock, Config::default(), origin::OriginDynamic {});
+        let driver = driver
+            .init_detect(Vcom::from_volts(-1.53).unwrap())
+            .unwrap();
+
+        assert_eq!(driver.get_dev_info().unwrap().lut_version, "M841_TFA5210");
+        assert_eq!(driver.panel_mirror, Mirror::Horizontal);
+        assert_eq!(driver.profile().unwrap().name, "Waveshare 10.3inch");
+
+        // the configured rotation is kept
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M841_TFA5210";
+        let config = Config {
+            rotation: Rotation::Rotate90,
+            ..Default::default()
+        };
+        let driver = IT8951::new_with_origin(mock, config, origin::OriginDynamic {});
+        let driver = driver.init_no_vcom_detect().unwrap();
+
+        assert_eq!(driver.rotation(), Rotation::Rotate90);
+        assert_eq!(driver.panel_mirror, Mirror::Horizontal);
+
+        // unknown LUT of a 1872x1404 panel, the 7.8inch and 10.3inch panels differ in the origin
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M841";
+        let driver = IT8951::new_with_origin(mock, Config::default(), origin::OriginDynamic {});
+
+        assert_eq!(
+            driver.init_no_vcom_detect().err(),
+            Some(Error::AmbiguousPanel)
+        );
+    }
+
+    #[test]
+    fn test_set_rotation() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = Rectangle::new(Point::new(10, 20), Size::new(30, 40));
+        assert!(driver.fill_solid(&area, Gray4::new(0x5)).is_ok());
+        assert_eq!(driver.size(), Size::new(1872, 1404));
+
+        driver.set_rotation(Rotation::Rotate90);
+        assert_eq!(driver.rotation(), Rotation::Rotate90);
+        assert_eq!(driver.size(), Size::new(1404, 1872));
+        assert!(driver.dirty_regions().is_empty());
+
+        // loads are rotated by the controller, refreshes by the driver
+        driver.interface.commands.clear();
+        assert!(driver.fill_solid(&area, Gray4::new(0x5)).is_ok());
+        assert!(driver.display_dirty(WaveformMode::GL16).is_ok());
+        let load = driver
+            .interface
+            .commands
+            .iter()
+            .find(|(cmd, _)| *cmd == command::IT8951_TCON_LD_IMG_AREA)
+            .unwrap();
+        assert_eq!(
+            load.1[0] & 0x0003,
+            memory_converter_settings::MemoryConverterRotation::Rotate90 as u16
+        );
+        assert!(driver.interface.commands.contains(&(
+            command::USDEF_I80_CMD_DPY_AREA,
+            vec![20, 1404 - 30 - 10, 40, 30, WaveformMode::GL16 as u16]
+        )));
+
+        driver.interface.commands.clear();
+        assert!(driver
+            .set_rotation_and_clear(Rotation::Rotate180, Gray4::WHITE, WaveformMode::Init)
+            .is_ok());
+        assert_eq!(driver.size(), Size::new(1872, 1404));
+        assert!(driver.dirty_regions().is_empty());
+        assert!(driver.interface.commands.contains(&(
+            command::USDEF_I80_CMD_DPY_AREA,
+            vec![0, 0, 1872, 1404, WaveformMode::Init as u16]
+        )));
+    }
+
+    #[test]
+    fn test_orientations_load_and_refresh_same_area() {
+        use origin::Mirror;
+        let area = Rectangle::new(Point::new(10, 20), Size::new(30, 40));
+        let panel = Size::new(1872, 1404);
+        for rotation in [
+            Rotation::Rotate0,
+            Rotation::Rotate90,
+            Rotation::Rotate180,
+            Rotation::Rotate270,
+        ] {
+            for mirror in [Mirror::None, Mirror::Horizontal, Mirror::Vertical] {
+                let mock = MockInterface::new();
+                let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+                let mut driver = driver.into_state::<Run>();
+                driver.dev_info = Some(DevInfo {
+                    panel_width: 1872,
+                    panel_height: 1404,
+                    memory_address: 0x001236E0,
+                    firmware_version: String::from("test"),
+                    lut_version: String::from("test"),
+                });
+                let orientation = Orientation::new(rotation, mirror);
+                driver.set_orientation(orientation);
+
+                assert!(driver.fill_solid(&area, Gray4::new(0x5)).is_ok());
+                assert!(driver.display_dirty(WaveformMode::GL16).is_ok());
+
+                // the controller rotates the loaded area
+                let (_, load) = driver
+                    .interface
+                    .commands
+                    .iter()
+                    .find(|(cmd, _)| *cmd == command::IT8951_TCON_LD_IMG_AREA)
+                    .unwrap();
+                let load_rotation = match load[0] & 0x0003 {
+                    0 => Rotation::Rotate0,
+                    1 => Rotation::Rotate90,
+                    2 => Rotation::Rotate180,
+                    _ => Rotation::Rotate270,
+                };
+                let loaded = Orientation::new(load_rotation, Mirror::None).area_to_panel(
+                    &Rectangle::new(
+                        Point::new(load[1] as i32, load[2] as i32),
+                        Size::new(load[3] as u32, load[4] as u32),
+                    ),
+                    panel,
+                );
+
+                let expected = orientation.area_to_panel(&area, panel);
+                assert_eq!(loaded, expected, "{:?}", orientation);
+                assert!(
+                    driver.interface.commands.contains(&(
+                        command::USDEF_I80_CMD_DPY_AREA,
+                        vec![
+                            expected.top_left.x as u16,
+                            expected.top_left.y as u16,
+                            expected.size.width as u16,
+                            expected.size.height as u16,
+                            WaveformMode::GL16 as u16
+                        ]
+                    )),
+                    "{:?}",
+                    orientation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_origin_top_right_mirrors_area() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopRight, Off>::new_with_origin(
+            mock,
+            Config::default(),
+            origin::OriginTopRight {},
+        );
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = Rectangle::new(Point::new(10, 20), Size::new(30, 40));
+        assert!(driver.fill_solid(&area, Gray4::new(0x5)).is_ok());
+        assert!(driver.display_dirty(WaveformMode::GL16).is_ok());
+        assert!(driver.interface.commands.contains(&(
+            command::USDEF_I80_CMD_DPY_AREA,
+            vec![1872 - 30 - 10, 20, 30, 40, WaveformMode::GL16 as u16]
+        )));
+    }
+
+    #[test]
+    fn test_uninitialized_driver_returns_errors() {
+        let mock = MockInterface::new();
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default())
+            .into_state::<Run>();
+
+        assert_eq!(driver.size(), Size::zero());
+        assert_eq!(driver.get_dev_info().err(), Some(Error::NotInitialized));
+        assert_eq!(driver.image_buffer(), Err(Error::NotInitialized));
+        assert_eq!(
+            driver.fill_solid(
+                &Rectangle::new(Point::zero(), Size::new(4, 4)),
+                Gray4::BLACK
+            ),
+            Err(Error::NotInitialized)
+        );
+        assert_eq!(
+            driver.draw_iter([Pixel(Point::zero(), Gray4::BLACK)]),
+            Err(Error::NotInitialized)
+        );
+        assert_eq!(
+            driver.alloc_image_buffer(16, 16, 8),
+            Err(Error::NotInitialized)
+        );
+    }
+
+    #[test]
+    fn test_config_validate() {
+        assert_eq!(Config::default().validate(), Ok(()));
+        for config in [
+            Config {
+                max_buffer_size: 1023,
+                ..Default::default()
+            },
+            Config {
+                max_buffer_size: 0,
+                ..Default::default()
+            },
+            Config {
+                lut_engines: 0,
+                ..Default::default()
+            },
+            Config {
+                dirty_regions: 0,
+                ..Default::default()
+            },
+            Config {
+                dirty_overdraw_percent: 101,
+                ..Default::default()
+            },
+        ] {
+            assert_eq!(config.validate(), Err(Error::InvalidConfig));
+
+            let mock = MockInterface::new();
+            let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+            assert_eq!(
+                driver.init(Vcom::from_millivolts(1530).unwrap()).err(),
+                Some(Error::InvalidConfig)
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors_instead_of_panics() {
+        let mock = MockInterface::new();
+        let config = Config {
+            max_buffer_size: 16,
+            ..Default::default()
+        };
+        let mut driver =
+            IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config).into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        // a row of 100 pixels needs 100 bytes
+        let area = Rectangle::new(Point::zero(), Size::new(100, 2));
+        assert_eq!(
+            driver.fill_solid(&area, Gray4::BLACK),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(
+            driver.fill_contiguous(&area, core::iter::repeat(Gray4::BLACK)),
+            Err(Error::BufferTooSmall)
+        );
+
+        let mut data = [0u8; 3];
+        assert_eq!(
+            driver.memory_burst_write(0x001236E0, &mut data),
+            Err(Error::Misaligned)
+        );
+        assert_eq!(
+            driver.memory_burst_read(0x001236E0, &mut data),
+            Err(Error::Misaligned)
+        );
+
+        // the end address of the buffer exceeds the 32 bit address space
+        let overflowing = ImageBuffer {
+            addr: u32::MAX - 10,
+            width: 100,
+            height: 100,
+            bpp: 8,
+        };
+        assert_eq!(
+            driver.reserve_image_buffer(overflowing),
+            Err(Error::OutOfMemory)
+        );
+        assert_eq!(
+            driver.free_image_buffer(overflowing),
+            Err(Error::InvalidImageBuffer)
+        );
+
+        // the default image buffer exceeds the 32 bit address space
+        driver.allocator = None;
+        if let Some(dev_info) = driver.dev_info.as_mut() {
+            dev_info.memory_address = u32::MAX - 10;
+        }
+        assert_eq!(
+            driver.free_image_buffer(overflowing),
+            Err(Error::OutOfMemory)
+        );
+    }
+
+    fn bounds_driver<O: Origin>(
+        origin: O,
+        rotation: Rotation,
+        area_policy: AreaPolicy,
+    ) -> IT8951<MockInterface, O, Run> {
+        let config = Config {
+            rotation,
+            area_policy,
+            ..Default::default()
+        };
+        let mut driver =
+            IT8951::new_with_origin(MockInterface::new(), config, origin).into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+        driver
+    }
+
+    fn check_area_bounds<O: Origin>(origin: fn() -> O) {
+        let panel = Size::new(1872, 1404);
+        let dpy_area = |area: &Rectangle| {
+            (
+                command::USDEF_I80_CMD_DPY_AREA,
+                vec![
+                    area.top_left.x as u16,
+                    area.top_left.y as u16,
+                    area.size.width as u16,
+                    area.size.height as u16,
+                    WaveformMode::GL16 as u16,
+                ],
+            )
+        };
+
+        for rotation in [
+            Rotation::Rotate0,
+            Rotation::Rotate90,
+            Rotation::Rotate180,
+            Rotation::Rotate270,
+        ] {
+            let orientation = Orientation::new(rotation, O::MIRROR);
+            let display = orientation.display_size(panel);
+            let (w, h) = (display.width as u16, display.height as u16);
+            let overlapping = AreaImgInfo {
+                area_x: w - 10,
+                area_y: h - 20,
+                area_w: 30,
+                area_h: 40,
+            };
+            let outside = AreaImgInfo {
+                area_x: w,
+                area_y: 0,
+                area_w: 10,
+                area_h: 10,
+            };
+            let overflowing = AreaImgInfo {
+                area_x: u16::MAX,
+                area_y: u16::MAX,
+                area_w: u16::MAX,
+                area_h: u16::MAX,
+            };
+            let full = AreaImgInfo {
+                area_x: 0,
+                area_y: 0,
+                area_w: w,
+                area_h: h,
+            };
+
+            let mut driver = bounds_driver(origin(), rotation, AreaPolicy::Reject);
+            for area in [overlapping, outside, overflowing] {
+                // also used by readback, double buffering and load alignment
+                assert_eq!(driver.panel_area_info(&area), Err(Error::InvalidArea));
+                assert_eq!(
+                    driver.display_area(&area, WaveformMode::GL16),
+                    Err(Error::InvalidArea),
+                    "{:?} {:?}",
+                    orientation,
+                    area
+                );
+                assert_eq!(
+                    driver.display_area_buf(&area, WaveformMode::GL16, 0x001236E0),
+                    Err(Error::InvalidArea)
+                );
+            }
+            assert!(driver.interface.commands.is_empty());
+            assert_eq!(driver.display_area(&full, WaveformMode::GL16), Ok(()));
+            assert!(driver
+                .interface
+                .commands
+                .contains(&dpy_area(&Rectangle::new(Point::zero(), panel))));
+
+            let mut driver = bounds_driver(origin(), rotation, AreaPolicy::Clip);
+            for area in [outside, overflowing] {
+                assert_eq!(driver.display_area(&area, WaveformMode::GL16), Ok(()));
+                assert_eq!(
+                    driver.display_area_buf(&area, WaveformMode::GL16, 0x001236E0),
+                    Ok(())
+                );
+            }
+            assert!(driver.interface.commands.is_empty());
+            assert_eq!(
+                driver.display_area(&overlapping, WaveformMode::GL16),
+                Ok(())
+            );
+            let clipped =
+                Rectangle::new(Point::new(w as i32 - 10, h as i32 - 20), Size::new(10, 20));
+            assert!(
+                driver
+                    .interface
+                    .commands
+                    .contains(&dpy_area(&orientation.area_to_panel(&clipped, panel))),
+                "{:?}",
+                orientation
+            );
+
+            // loads are checked against the rotation of the image settings
+            let settings = MemoryConverterSetting {
+                rotation: (&rotation).into(),
+                ..Default::default()
+            };
+            let data = [0u8; 2];
+            let corner = AreaImgInfo {
+                area_x: w - 4,
+                area_y: h - 1,
+                area_w: 4,
+                area_h: 1,
+            };
+            assert_eq!(
+                driver.load_image_area(0x001236E0, settings, &corner, &data),
+                Ok(())
+            );
+            for area in [overlapping, outside, overflowing] {
+                assert_eq!(
+                    driver.load_image_area(0x001236E0, settings, &area, &data),
+                    Err(Error::InvalidArea)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_area_bounds_origin_top_left() {
+        check_area_bounds(|| origin::OriginTopLeft {});
+    }
+
+    #[test]
+    fn test_area_bounds_origin_top_right() {
+        check_area_bounds(|| origin::OriginTopRight {});
+    }
+
+    #[test]
+    fn test_area_bounds_origin_dynamic() {
+        check_area_bounds(|| origin::OriginDynamic {});
+    }
+
+    #[test]
+    fn test_load_image_checks_data_length() {
+        let mut driver = bounds_driver(
+            origin::OriginTopLeft {},
+            Rotation::Rotate0,
+            AreaPolicy::Reject,
+        );
+        let frame = vec![0u8; 1872 * 1404 / 2];
+        assert_eq!(
+            driver.load_image(0x001236E0, MemoryConverterSetting::default(), &frame),
+            Ok(())
+        );
+        let too_large = vec![0u8; 1872 * 1404 / 2 + 2];
+        assert_eq!(
+            driver.load_image(0x001236E0, MemoryConverterSetting::default(), &too_large),
+            Err(Error::InvalidArea)
+        );
+    }
+
+    #[test]
+    fn test_self_test_healthy_controller() {
+        fn clock() -> core::time::Duration {
+            // every clock read takes 1ms
+            static TICKS: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+            core::time::Duration::from_millis(
+                TICKS.fetch_add(1, core::sync::atomic::Ordering::Relaxed),
+            )
+        }
+
+        let mut mock = MockInterface::new();
+        mock.firmware_version = "SWv_0.2.1.T";
+        mock.lut_version = "M841_TFA5210";
+        let config = Config {
+            clock: Some(clock),
+            ..Default::default()
+        };
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config)
+            .init(Vcom::from_millivolts(1530).unwrap())
+            .unwrap();
+
+        let mut content = [0x12, 0x34, 0x56, 0x78];
+        driver.memory_burst_write(0x1000, &mut content).unwrap();
+
+        let report = driver.self_test(None);
+        assert!(report.passed(), "{:?}", report);
+        let dev_info = report.dev_info.unwrap();
+        assert_eq!(dev_info.dev_info.firmware_version, "SWv_0.2.1.T");
+        assert!(dev_info.issues.is_empty());
+        let memory = report.memory.unwrap();
+        // free memory after the default image buffer is tested
+        let free = driver.image_buffer().unwrap().end_addr().unwrap();
+        assert_eq!(
+            (memory.address, memory.len),
+            (free.next_multiple_of(4), 1024)
+        );
+        assert_eq!(memory.errors, 0);
+        assert_eq!(report.registers, Ok(Vec::new()));
+        let response_time = report.response_time.unwrap().unwrap();
+        assert_eq!(response_time.samples, 8);
+        assert!(response_time.min <= response_time.mean && response_time.mean <= response_time.max);
+        assert!(report.refresh.is_none());
+
+        // the displayed image is not touched and the tested memory is released
+        let mut read = [0u8; 4];
+        driver.memory_burst_read(0x1000, &mut read).unwrap();
+        assert_eq!(read, [0x12, 0x34, 0x56, 0x78]);
+        assert!(driver
+            .allocator
+            .as_ref()
+            .is_some_and(|a| a.allocated().is_empty()));
+        assert!(!driver
+            .interface
+            .commands
+            .iter()
+            .any(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA));
+
+        let report = driver.self_test(Some(WaveformMode::GrayscaleClearing16));
+        assert!(report.passed());
+        assert!(report.refresh.unwrap().unwrap().duration.is_some());
+        let refreshes = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .count();
+        assert_eq!(refreshes, 2);
+    }
+
+    #[test]
+    fn test_self_test_reports_problems() {
+        let mock = MockInterface::new();
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default())
+            .into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+        // LISAR ignores the written patterns
+        driver.interface.read_only.push(register::LISAR);
+        driver.interface.registers.insert(register::LISAR, 0x1234);
+
+        let report = driver.self_test(None);
+        assert!(!report.passed());
+        assert_eq!(
+            report.dev_info.unwrap().issues,
+            vec![
+                diagnostics::DevInfoIssue::MissingFirmwareVersion,
+                diagnostics::DevInfoIssue::MissingLutVersion,
+                diagnostics::DevInfoIssue::ChangedSinceInit,
+            ]
+        );
+        assert_eq!(
+            report.registers,
+            Ok(vec![
+                diagnostics::RegisterMismatch {
+                    register: register::LISAR,
+                    written: 0xA5A4,
+                    read: 0x1234,
+                },
+                diagnostics::RegisterMismatch {
+                    register: register::LISAR,
+                    written: 0x5A5A,
+                    read: 0x1234,
+                },
+            ])
+        );
+        assert_eq!(report.response_time, Ok(None));
+
+        // a failed pattern transfer still releases the tested memory
+        driver.interface.burst_reads_until_error = Some(0);
+        let report = driver.self_test(None);
+        assert_eq!(
+            report.memory,
+            Err(Error::Interface(interface::Error::SpiError))
+        );
+        assert!(driver
+            .allocator
+            .as_ref()
+            .is_some_and(|a| a.allocated().is_empty()));
+        driver.interface.burst_reads_until_error = None;
+
+        // without dev info the free memory can not be located
+        driver.dev_info = None;
+        driver.allocator = None;
+        let report = driver.self_test(None);
+        assert_eq!(report.memory, Err(Error::NotInitialized));
+    }
+
+    fn last_display_mode(
+        driver: &IT8951<MockInterface, origin::OriginTopLeft, Run>,
+    ) -> Option<u16> {
+        driver
+            .interface
+            .commands
+            .iter()
+            .rev()
+            .find(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_AREA)
+            .map(|(_, args)| args[4])
+    }
+
+    #[test]
+    fn test_waveform_table_from_lut_version() {
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M641";
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default())
+            .init(Vcom::from_millivolts(1530).unwrap())
+            .unwrap();
+        assert_eq!(driver.waveform_table(), WaveformTable::M641);
+
+        let area = AreaImgInfo {
+            area_x: 0,
+            area_y: 0,
+            area_w: 64,
+            area_h: 64,
+        };
+        assert_eq!(driver.display_area(&area, WaveformMode::A2), Ok(()));
+        assert_eq!(last_display_mode(&driver), Some(4));
+
+        let commands = driver.interface.commands.len();
+        assert_eq!(
+            driver.display_area(&area, WaveformMode::DU4),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert_eq!(
+            driver.display_area_buf(&area, WaveformMode::GLR16, 0x1000),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert_eq!(driver.interface.commands.len(), commands);
+
+        // the dirty regions are kept for a unsupported waveform
+        driver
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(8, 8)),
+                Gray4::BLACK,
+            )
+            .unwrap();
+        let regions = driver.dirty_regions().to_vec();
+        let commands = driver.interface.commands.len();
+        assert_eq!(
+            driver.display_dirty(WaveformMode::DU4),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert_eq!(driver.dirty_regions(), &regions[..]);
+        assert_eq!(driver.interface.commands.len(), commands);
+        driver.clear_dirty();
+
+        // the policy selects DU4 for 4 gray levels, which falls back to GC16
+        driver
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(8, 8)),
+                Gray4::new(0x5),
+            )
+            .unwrap();
+        assert_eq!(driver.display_dirty(UpdateMode::Auto), Ok(()));
+        assert_eq!(
+            last_display_mode(&driver),
+            Some(WaveformMode::GrayscaleClearing16 as u16)
+        );
+
+        // without GC16 the region can not be refreshed and is dropped
+        driver.config.waveform_table =
+            Some(WaveformTable::M641.without(WaveformMode::GrayscaleClearing16));
+        driver
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 0), Size::new(8, 8)),
+                Gray4::new(0x5),
+            )
+            .unwrap();
+        assert_eq!(
+            driver.display_dirty(UpdateMode::Auto),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert!(driver.dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn test_waveform_table_from_config() {
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M641";
+        let config = Config {
+            waveform_table: Some(WaveformTable::STANDARD.with(WaveformMode::DU4, 5)),
+            ..Default::default()
+        };
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config)
+            .init(Vcom::from_millivolts(1530).unwrap())
+            .unwrap();
+
+        let area = AreaImgInfo {
+            area_x: 0,
+            area_y: 0,
+            area_w: 64,
+            area_h: 64,
+        };
+        assert_eq!(driver.display_area(&area, WaveformMode::DU4), Ok(()));
+        assert_eq!(last_display_mode(&driver), Some(5));
+    }
+
+    #[test]
+    fn test_ghosting_promotion_to_unsupported_waveform() {
+        let config = Config {
+            ghosting: Some(GhostingConfig {
+                budget: 1,
+                ..Default::default()
+            }),
+            waveform_table: Some(
+                WaveformTable::STANDARD.without(WaveformMode::GrayscaleClearing16),
+            ),
+            ..Default::default()
+        };
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(MockInterface::new(), config)
+            .init(Vcom::from_millivolts(1530).unwrap())
+            .unwrap();
+
+        let area = AreaImgInfo {
+            area_x: 0,
+            area_y: 0,
+            area_w: 64,
+            area_h: 64,
+        };
+        assert_eq!(driver.display_area(&area, WaveformMode::A2), Ok(()));
+        let commands = driver.interface.commands.len();
+        // the promoted GC16 refresh fails without changing the counters
+        assert_eq!(
+            driver.display_area_buf(&area, WaveformMode::A2, 0x1000),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert_eq!(driver.ghosting_budget().unwrap().counter(0, 0), 1);
+        assert!(!driver.interface.commands[commands..]
+            .iter()
+            .any(|(cmd, _)| *cmd == command::USDEF_I80_CMD_DPY_BUF_AREA));
+    }
+
+    #[test]
+    fn test_waveform_table_from_profile() {
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M841";
+        let driver = IT8951::new_with_profile(mock, Config::default(), &profiles::WAVESHARE_6INCH);
+        let driver = driver.init_no_vcom().unwrap();
+        // the profile takes precedence over the LUT version
+        assert_eq!(driver.waveform_table(), WaveformTable::M641);
+
+        let mut mock = MockInterface::new();
+        mock.lut_version = "M641";
+        let config = Config {
+            waveform_table: Some(WaveformTable::STANDARD),
+            ..Default::default()
+        };
+        let driver = IT8951::new_with_profile(mock, config, &profiles::WAVESHARE_6INCH);
+        let driver = driver.init_no_vcom().unwrap();
+        assert_eq!(driver.waveform_table(), WaveformTable::STANDARD);
+    }
+
+    #[test]
+    fn test_staging_buffer_is_reused() {
+        let config = Config {
+            max_buffer_size: 64,
+            ..Default::default()
+        };
+        let mut driver = IT8951::<_, origin::OriginTopLeft, Off>::new(MockInterface::new(), config)
+            .init(Vcom::from_millivolts(1530).unwrap())
+            .unwrap();
+        assert_eq!(driver.staging.len(), 64);
+        let staging = driver.staging.as_ptr();
+
+        // 100 pixels need 52 bytes per row, every row is a separate load
+        let area = Rectangle::new(Point::new(3, 5), Size::new(100, 30));
+        driver.fill_solid(&area, Gray4::new(0x3)).unwrap();
+        driver
+            .fill_contiguous(&area, core::iter::repeat(Gray4::new(0x7)))
+            .unwrap();
+        let mut screenshot = vec![0u8; 1872 * 1404];
+        driver.screenshot(&mut screenshot).unwrap();
+
+        let loads = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::IT8951_TCON_LD_IMG_AREA)
+            .count();
+        assert_eq!(loads, 60);
+        assert_eq!(driver.staging.as_ptr(), staging);
+        assert_eq!(driver.staging.len(), 64);
+    }
+
+    #[test]
+    fn test_fill_contiguous_with_missing_colors() {
+        let mut driver =
+            IT8951::<_, origin::OriginTopLeft, Off>::new(MockInterface::new(), Config::default())
+                .init(Vcom::from_millivolts(1530).unwrap())
+                .unwrap();
+
+        // the colors of 2 complete rows and a part of the third row, which is dropped
+        let area = Rectangle::new(Point::new(0, 0), Size::new(8, 4));
+        driver
+            .fill_contiguous(&area, core::iter::repeat_n(Gray4::WHITE, 20))
+            .unwrap();
+        assert!(driver
+            .interface
+            .commands
+            .contains(&(command::IT8951_TCON_LD_IMG_AREA, vec![0x0020, 0, 0, 8, 2])));
+    }
+
+    #[test]
+    fn test_driver_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Config>();
+        assert_send::<IT8951<MockInterface, origin::OriginTopLeft, Off>>();
+        assert_send::<IT8951<MockInterface, origin::OriginDynamic, Run>>();
+    }
+
+    #[test]
+    fn test_finished_lut_engine_releases_area() {
+        let mut mock = MockInterface::new();
+        // engine 0 starts the first update, engine 1 the second, engine 0 finishes
+        mock.read_data.extend([0, 0b01, 0b01, 0b11, 0b10, 0b11]);
+        let config = Config {
+            lut_engines: 2,
+            ..Default::default()
+        };
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, config);
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 1872,
+            panel_height: 1404,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let area = |x, y| AreaImgInfo {
+            area_x: x,
+            area_y: y,
+            area_w: 100,
+            area_h: 100,
+        };
+        driver.display_area(&area(0, 0), WaveformMode::A2).unwrap();
+        driver
+            .display_area(&area(500, 0), WaveformMode::A2)
+            .unwrap();
+        // overlaps the finished first update, but not the running second one
+        driver
+            .display_area(&area(50, 50), WaveformMode::A2)
+            .unwrap();
+        assert!(driver.interface.read_data.is_empty());
+
+        let reads = driver
+            .interface
+            .commands
+            .iter()
+            .filter(|(cmd, _)| *cmd == command::IT8951_TCON_REG_RD)
+            .count();
+        assert_eq!(reads, 6);
+        // A2 updates are widened to the alignment, only the engines are compared
+        let engines: Vec<u16> = driver
+            .in_flight
+            .iter()
+            .map(|(engines, _)| *engines)
+            .collect();
+        assert_eq!(engines, [0b10, 0b01]);
+    }
+
+    #[test]
+    fn test_area_img_info_from_rectangle() {
+        let area = Rectangle::new(Point::new(10, 20), Size::new(30, 40));
+        assert_eq!(
+            AreaImgInfo::try_from(&area),
+            Ok(AreaImgInfo {
+                area_x: 10,
+                area_y: 20,
+                area_w: 30,
+                area_h: 40,
+            })
+        );
+        assert_eq!(
+            Rectangle::from(&AreaImgInfo::try_from(&area).unwrap()),
+            area
+        );
+
+        for area in [
+            Rectangle::new(Point::new(-1, 0), Size::new(30, 40)),
+            Rectangle::new(Point::new(0, 0x10000), Size::new(30, 40)),
+            Rectangle::new(Point::zero(), Size::new(0x10000, 40)),
+        ] {
+            assert_eq!(AreaImgInfo::try_from(&area), Err(Error::InvalidArea));
+        }
+    }
+
+    #[test]
+    fn test_double_buffered_ignores_offscreen_pixels() {
+        let mock = MockInterface::new();
+        let driver = IT8951::<_, origin::OriginTopLeft, Off>::new(mock, Config::default());
+        let mut driver = driver.into_state::<Run>();
+        driver.dev_info = Some(DevInfo {
+            panel_width: 16,
+            panel_height: 8,
+            memory_address: 0x001236E0,
+            firmware_version: String::from("test"),
+            lut_version: String::from("test"),
+        });
+
+        let mut epd = driver.into_double_buffered(true).unwrap();
+        let front = epd.front_buffer();
+        epd.draw_iter([
+            Pixel(Point::new(i32::MIN, i32::MIN), Gray4::BLACK),
+            Pixel(Point::new(5, 3), Gray4::BLACK),
+            Pixel(Point::new(i32::MAX, i32::MAX), Gray4::BLACK),
+        ])
+        .unwrap();
+        epd.flip(WaveformMode::GL16).unwrap();
+
+        // only the word of the visible pixel is copied forward
+        let last_write = epd
+            .driver()
+            .interface
+            .commands
+            .iter()
+            .rev()
+            .find(|(cmd, _)| *cmd == command::IT8951_TCON_MEM_BST_WR)
+            .unwrap()
+            .clone();
+        assert_eq!(
+            last_write.1[..],
+            [
+                (front.addr + 3 * 16 + 4) as u16,
+                (front.addr >> 16) as u16,
+                2,
+                0
+            ]
+        );
+    }
 }
@@ -82,3 +82,26 @@ impl From<&Rotation> for MemoryConverterRotation {
         }
     }
 }
+
+impl From<&MemoryConverterRotation> for Rotation {
+    fn from(rotation: &MemoryConverterRotation) -> Self {
+        match rotation {
+            MemoryConverterRotation::Rotate0 => Rotation::Rotate0,
+            MemoryConverterRotation::Rotate90 => Rotation::Rotate90,
+            MemoryConverterRotation::Rotate180 => Rotation::Rotate180,
+            MemoryConverterRotation::Rotate270 => Rotation::Rotate270,
+        }
+    }
+}
+
+impl MemoryConverterBitPerPixel {
+    /// Number of bits used by a pixel in the transferred data, 3bpp pixels use 4 bits
+    pub(crate) fn bits(self) -> u32 {
+        match self {
+            MemoryConverterBitPerPixel::BitsPerPixel2 => 2,
+            MemoryConverterBitPerPixel::BitsPerPixel3 => 4,
+            MemoryConverterBitPerPixel::BitsPerPixel4 => 4,
+            MemoryConverterBitPerPixel::BitsPerPixel8 => 8,
+        }
+    }
+}
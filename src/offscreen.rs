@@ -0,0 +1,97 @@
+//! Off-screen drawing into controller image buffers
+
+use embedded_graphics_core::{pixelcolor::Gray4, prelude::*, primitives::Rectangle};
+
+use crate::{
+    image_buffer::ImageBuffer, interface::IT8951Interface, origin::Origin, AreaImgInfo, Error, Run,
+    WaveformMode, IT8951,
+};
+
+/// Draw target rendering into a image buffer instead of the default image buffer
+/// The panel keeps showing its content until the surface is presented
+pub struct OffscreenSurface<'a, Interface, TOrigin: Origin> {
+    driver: &'a mut IT8951<Interface, TOrigin, Run>,
+    buffer: ImageBuffer,
+    alpha: Option<u8>,
+}
+
+impl<'a, Interface: IT8951Interface, TOrigin: Origin> OffscreenSurface<'a, Interface, TOrigin> {
+    /// Creates a new surface for the given buffer
+    /// The controller addresses image buffers with the panel geometry,
+    /// therefore the buffer must be a 8bpp buffer with the panel size
+    pub fn new(
+        driver: &'a mut IT8951<Interface, TOrigin, Run>,
+        buffer: ImageBuffer,
+    ) -> Result<Self, Error> {
+        let frame = driver.image_buffer()?;
+        if buffer.bpp != frame.bpp || buffer.width != frame.width || buffer.height != frame.height {
+            return Err(Error::InvalidImageBuffer);
+        }
+
+        Ok(OffscreenSurface {
+            driver,
+            buffer,
+            alpha: None,
+        })
+    }
+
+    /// Alpha blend the surface with the panel content when presented
+    /// An alpha of 0xFF shows only the surface content, 0x00 keeps the panel content
+    pub fn with_alpha(mut self, alpha: u8) -> Self {
+        self.alpha = Some(alpha);
+        self
+    }
+
+    /// The image buffer of the surface
+    pub fn buffer(&self) -> ImageBuffer {
+        self.buffer
+    }
+
+    /// Refresh a specific area of the display with the surface content
+    pub fn present(&mut self, area_info: &AreaImgInfo, mode: WaveformMode) -> Result<(), Error> {
+        match self.alpha {
+            Some(alpha) => {
+                self.driver
+                    .display_area_buf_blended(area_info, mode, self.buffer.addr, alpha)
+            }
+            None => self
+                .driver
+                .display_area_buf(area_info, mode, self.buffer.addr),
+        }
+    }
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> DrawTarget
+    for OffscreenSurface<'_, Interface, TOrigin>
+{
+    type Color = Gray4;
+
+    type Error = Error;
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.driver.fill_solid_at(self.buffer.addr, area, color)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.driver
+            .fill_contiguous_at(self.buffer.addr, area, colors)
+    }
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.driver.draw_iter_at(self.buffer.addr, pixels)
+    }
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> OriginDimensions
+    for OffscreenSurface<'_, Interface, TOrigin>
+{
+    fn size(&self) -> Size {
+        self.driver.size()
+    }
+}
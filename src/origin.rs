@@ -1,91 +1,349 @@
-//! Trait to manage origin of the coordinate system
+//! Orientation of the display content on the panel
+
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+
+use crate::Rotation;
 
 /// Origin for TopLeft corner
 pub struct OriginTopLeft;
-/// Origin for TopRight corner
+/// Origin for TopRight corner, the panel is mirrored horizontally
 pub struct OriginTopRight;
+/// Origin selected at runtime, e.g. by a panel profile
+/// Starts with the TopLeft corner
+pub struct OriginDynamic;
+
+/// Origin of the panel coordinate system
+/// Implement it for panels which are mounted mirrored
+pub trait Origin {
+    /// Mirroring of the panel
+    const MIRROR: Mirror;
+}
+
+impl Origin for OriginTopLeft {
+    const MIRROR: Mirror = Mirror::None;
+}
+
+impl Origin for OriginTopRight {
+    const MIRROR: Mirror = Mirror::Horizontal;
+}
 
-mod private {
-    use core::ops::BitXor;
-    use embedded_graphics_core::{prelude::Point, primitives::Rectangle};
+impl Origin for OriginDynamic {
+    const MIRROR: Mirror = Mirror::None;
+}
 
-    use crate::{AreaImgInfo, DevInfo};
+/// Mirroring in panel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Mirror {
+    /// Not mirrored
+    #[default]
+    None,
+    /// Mirrored along the vertical axis, the panel origin is in the TopRight corner
+    Horizontal,
+    /// Mirrored along the horizontal axis, the panel origin is in the BottomLeft corner
+    Vertical,
+}
 
-    pub trait Sealed {
-        /// Transforms the AreaImgInfo
-        fn transform(area_img_info: &mut AreaImgInfo, dev_info: &DevInfo);
+/// Rotation and mirroring of the display content on the panel
+/// The content is rotated first and then mirrored in panel coordinates,
+/// which covers all 8 orientations of a rectangular panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Orientation {
+    /// Rotation of the display content
+    pub rotation: Rotation,
+    /// Mirroring after the rotation
+    pub mirror: Mirror,
+}
 
-        /// Determines the right position in a area image buffer for a given pixel
-        fn bit_and_byte_pos(
-            area: &Rectangle,
-            point: Point,
-            nibbles_per_row: usize,
-            row: usize,
-            start_row: usize,
-        ) -> (usize, i32);
+impl Orientation {
+    /// Orientation from rotation and mirroring
+    pub const fn new(rotation: Rotation, mirror: Mirror) -> Self {
+        Orientation { rotation, mirror }
     }
 
-    impl Sealed for super::OriginTopLeft {
-        #[inline(always)]
-        fn transform(_: &mut AreaImgInfo, _: &DevInfo) {
-            // NoOp
+    /// Adds a mirroring in panel coordinates
+    /// Mirroring horizontally and vertically is the same as rotating by 180 degree
+    pub fn mirrored(self, mirror: Mirror) -> Self {
+        use Mirror::*;
+        match (self.mirror, mirror) {
+            (m, None) | (None, m) => Orientation::new(self.rotation, m),
+            (Horizontal, Horizontal) | (Vertical, Vertical) => {
+                Orientation::new(self.rotation, None)
+            }
+            (Horizontal, Vertical) | (Vertical, Horizontal) => {
+                Orientation::new(rotate_180(self.rotation), None)
+            }
         }
+    }
 
-        #[inline(always)]
-        fn bit_and_byte_pos(
-            area: &Rectangle,
-            point: Point,
-            nibbles_per_row: usize,
-            row: usize,
-            start_row: usize,
-        ) -> (usize, i32) {
-            let u16_pos = ((point.x - (area.top_left.x / 4 * 4)) / 2) as usize
-                + nibbles_per_row * (row - start_row);
-
-            // swap last pixel to map little endian behavior
-            let byte_pos = u16_pos.bitxor(0x00001);
-
-            // little endian layout
-            // [P3, P2 | P1, P0]
-            let bit_pos = (point.x % 2) * 4;
-
-            (byte_pos, bit_pos)
+    /// Size of the display for a panel
+    pub fn display_size(&self, panel: Size) -> Size {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => panel,
+            Rotation::Rotate90 | Rotation::Rotate270 => Size::new(panel.height, panel.width),
         }
     }
 
-    impl Sealed for super::OriginTopRight {
-        #[inline(always)]
-        fn transform(area_img_info: &mut AreaImgInfo, dev_info: &DevInfo) {
-            area_img_info.area_x =
-                dev_info.panel_width - area_img_info.area_x - area_img_info.area_w;
+    /// Transforms a area in display coordinates to panel coordinates
+    pub fn area_to_panel(&self, area: &Rectangle, panel: Size) -> Rectangle {
+        use Rotation::*;
+        let (pw, ph) = (panel.width as i32, panel.height as i32);
+        let (x, y) = (area.top_left.x, area.top_left.y);
+        let (w, h) = (area.size.width as i32, area.size.height as i32);
+
+        let (x, y, w, h) = match self.rotation {
+            Rotate0 => (x, y, w, h),
+            Rotate90 => (y, ph - w - x, h, w),
+            Rotate180 => (pw - w - x, ph - h - y, w, h),
+            Rotate270 => (pw - h - y, x, h, w),
+        };
+        let (x, y) = match self.mirror {
+            Mirror::None => (x, y),
+            Mirror::Horizontal => (pw - w - x, y),
+            Mirror::Vertical => (x, ph - h - y),
+        };
+
+        Rectangle::new(Point::new(x, y), Size::new(w as u32, h as u32))
+    }
+
+    /// Transforms a area in panel coordinates to display coordinates
+    pub fn area_to_display(&self, area: &Rectangle, panel: Size) -> Rectangle {
+        use Rotation::*;
+        let (pw, ph) = (panel.width as i32, panel.height as i32);
+        let (x, y) = (area.top_left.x, area.top_left.y);
+        let (w, h) = (area.size.width as i32, area.size.height as i32);
+
+        let (x, y) = match self.mirror {
+            Mirror::None => (x, y),
+            Mirror::Horizontal => (pw - w - x, y),
+            Mirror::Vertical => (x, ph - h - y),
+        };
+        let (x, y, w, h) = match self.rotation {
+            Rotate0 => (x, y, w, h),
+            Rotate90 => (ph - h - y, x, h, w),
+            Rotate180 => (pw - w - x, ph - h - y, w, h),
+            Rotate270 => (y, pw - w - x, h, w),
+        };
+
+        Rectangle::new(Point::new(x, y), Size::new(w as u32, h as u32))
+    }
+
+    /// Transforms a pixel position in display coordinates to panel coordinates
+    pub fn point_to_panel(&self, point: Point, panel: Size) -> Point {
+        self.area_to_panel(&Rectangle::new(point, Size::new(1, 1)), panel)
+            .top_left
+    }
+
+    /// Transforms a pixel position in panel coordinates to display coordinates
+    pub fn point_to_display(&self, point: Point, panel: Size) -> Point {
+        self.area_to_display(&Rectangle::new(point, Size::new(1, 1)), panel)
+            .top_left
+    }
+
+    /// Mirroring of loaded pixels in display coordinates
+    /// The controller only rotates loaded pixels, the mirroring is done before
+    pub(crate) fn load_flip(&self, panel: Size) -> Flip {
+        let (x, y) = match self.mirror {
+            Mirror::None => (false, false),
+            Mirror::Horizontal => (true, false),
+            Mirror::Vertical => (false, true),
+        };
+        // the panel x axis is the display y axis
+        let (x, y) = match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (x, y),
+            Rotation::Rotate90 | Rotation::Rotate270 => (y, x),
+        };
+        Flip {
+            x,
+            y,
+            size: self.display_size(panel),
         }
+    }
+}
+
+fn rotate_180(rotation: Rotation) -> Rotation {
+    match rotation {
+        Rotation::Rotate0 => Rotation::Rotate180,
+        Rotation::Rotate90 => Rotation::Rotate270,
+        Rotation::Rotate180 => Rotation::Rotate0,
+        Rotation::Rotate270 => Rotation::Rotate90,
+    }
+}
+
+/// Mirroring in display coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Flip {
+    /// Mirror the x axis
+    pub x: bool,
+    /// Mirror the y axis
+    pub y: bool,
+    /// Display size
+    pub size: Size,
+}
 
-        #[inline(always)]
-        fn bit_and_byte_pos(
-            area: &Rectangle,
-            point: Point,
-            nibbles_per_row: usize,
-            row: usize,
-            start_row: usize,
-        ) -> (usize, i32) {
-            let u16_pos =
-                nibbles_per_row - 1 - ((point.x - (area.top_left.x / 4 * 4)) / 2) as usize
-                    + nibbles_per_row * (row - start_row);
-
-            // swap last pixel to map little endian behavior
-            let byte_pos = u16_pos.bitxor(0x00001);
-
-            // little endian layout
-            // [P3, P2 | P1, P0]
-            let bit_pos = ((point.x + 1) % 2) * 4;
-
-            (byte_pos, bit_pos)
+impl Flip {
+    /// Flip of a display which is not mirrored
+    #[cfg(test)]
+    pub(crate) fn none(size: Size) -> Self {
+        Flip {
+            x: false,
+            y: false,
+            size,
         }
     }
+
+    pub(crate) fn point(&self, point: Point) -> Point {
+        let x = if self.x {
+            self.size.width as i32 - 1 - point.x
+        } else {
+            point.x
+        };
+        let y = if self.y {
+            self.size.height as i32 - 1 - point.y
+        } else {
+            point.y
+        };
+        Point::new(x, y)
+    }
+
+    pub(crate) fn rect(&self, area: &Rectangle) -> Rectangle {
+        let x = if self.x {
+            self.size.width as i32 - area.size.width as i32 - area.top_left.x
+        } else {
+            area.top_left.x
+        };
+        let y = if self.y {
+            self.size.height as i32 - area.size.height as i32 - area.top_left.y
+        } else {
+            area.top_left.y
+        };
+        Rectangle::new(Point::new(x, y), area.size)
+    }
 }
 
-/// Origin trait to transforrm AreaImgInfo before sending to controller
-pub trait Origin: private::Sealed {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PANEL: Size = Size::new(12, 8);
+
+    const ROTATIONS: [Rotation; 4] = [
+        Rotation::Rotate0,
+        Rotation::Rotate90,
+        Rotation::Rotate180,
+        Rotation::Rotate270,
+    ];
+
+    const MIRRORS: [Mirror; 3] = [Mirror::None, Mirror::Horizontal, Mirror::Vertical];
+
+    fn orientations() -> impl Iterator<Item = Orientation> {
+        ROTATIONS
+            .into_iter()
+            .flat_map(|r| MIRRORS.into_iter().map(move |m| Orientation::new(r, m)))
+    }
+
+    #[test]
+    fn test_corners() {
+        use Mirror::*;
+        use Rotation::*;
+        // panel position of the display origin
+        let cases = [
+            (Rotate0, None, Point::new(0, 0)),
+            (Rotate0, Horizontal, Point::new(11, 0)),
+            (Rotate0, Vertical, Point::new(0, 7)),
+            (Rotate90, None, Point::new(0, 7)),
+            (Rotate90, Horizontal, Point::new(11, 7)),
+            (Rotate90, Vertical, Point::new(0, 0)),
+            (Rotate180, None, Point::new(11, 7)),
+            (Rotate180, Horizontal, Point::new(0, 7)),
+            (Rotate180, Vertical, Point::new(11, 0)),
+            (Rotate270, None, Point::new(11, 0)),
+            (Rotate270, Horizontal, Point::new(0, 0)),
+            (Rotate270, Vertical, Point::new(11, 7)),
+        ];
+        for (rotation, mirror, expected) in cases {
+            let orientation = Orientation::new(rotation, mirror);
+            assert_eq!(
+                orientation.point_to_panel(Point::zero(), PANEL),
+                expected,
+                "{:?}",
+                orientation
+            );
+        }
+    }
+
+    #[test]
+    fn test_points_roundtrip() {
+        for orientation in orientations() {
+            let display = orientation.display_size(PANEL);
+            let panel_area = Rectangle::new(Point::zero(), PANEL);
+            let mut seen = alloc::vec::Vec::new();
+            for point in Rectangle::new(Point::zero(), display).points() {
+                let panel = orientation.point_to_panel(point, PANEL);
+                assert!(panel_area.contains(panel), "{:?}", orientation);
+                assert_eq!(orientation.point_to_display(panel, PANEL), point);
+                seen.push(panel);
+            }
+            seen.sort_by_key(|p| (p.y, p.x));
+            seen.dedup();
+            assert_eq!(seen.len(), (PANEL.width * PANEL.height) as usize);
+        }
+    }
+
+    #[test]
+    fn test_areas_match_points() {
+        let area = Rectangle::new(Point::new(1, 2), Size::new(3, 5));
+        for orientation in orientations() {
+            let panel = orientation.area_to_panel(&area, PANEL);
+            let mut points: alloc::vec::Vec<Point> = area
+                .points()
+                .map(|p| orientation.point_to_panel(p, PANEL))
+                .collect();
+            let mut expected: alloc::vec::Vec<Point> = panel.points().collect();
+            points.sort_by_key(|p| (p.y, p.x));
+            expected.sort_by_key(|p| (p.y, p.x));
+            assert_eq!(points, expected, "{:?}", orientation);
+            assert_eq!(orientation.area_to_display(&panel, PANEL), area);
+        }
+    }
+
+    #[test]
+    fn test_load_flip() {
+        // the controller rotates the flipped pixels to the mirrored panel position
+        for orientation in orientations() {
+            let flip = orientation.load_flip(PANEL);
+            let rotation = Orientation::new(orientation.rotation, Mirror::None);
+            let display = orientation.display_size(PANEL);
+            for point in Rectangle::new(Point::zero(), display).points() {
+                assert_eq!(
+                    rotation.point_to_panel(flip.point(point), PANEL),
+                    orientation.point_to_panel(point, PANEL),
+                    "{:?}",
+                    orientation
+                );
+            }
+
+            let area = Rectangle::new(Point::new(1, 2), Size::new(3, 5));
+            assert_eq!(
+                rotation.area_to_panel(&flip.rect(&area), PANEL),
+                orientation.area_to_panel(&area, PANEL)
+            );
+        }
+    }
 
-impl Origin for OriginTopLeft {}
-impl Origin for OriginTopRight {}
+    #[test]
+    fn test_mirrored() {
+        for orientation in orientations() {
+            for mirror in MIRRORS {
+                let combined = orientation.mirrored(mirror);
+                for point in [Point::new(0, 0), Point::new(3, 1), Point::new(5, 7)] {
+                    let panel = orientation.point_to_panel(point, PANEL);
+                    let panel =
+                        Orientation::new(Rotation::Rotate0, mirror).point_to_panel(panel, PANEL);
+                    assert_eq!(combined.point_to_panel(point, PANEL), panel);
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,6 @@
-use core::{borrow::Borrow, marker::PhantomData};
+use core::borrow::Borrow;
 
-use crate::{serialization_helper::get_nibbles_per_row, AreaImgInfo, Origin};
-use alloc::vec::Vec;
+use crate::{origin::Flip, serialization_helper::get_nibbles_per_row, AreaImgInfo, Error};
 use embedded_graphics_core::{
     pixelcolor::Gray4,
     prelude::*,
@@ -10,58 +9,79 @@ use embedded_graphics_core::{
 };
 
 /// Converts a list of Pixels (pos, color) into frame buffer segements with area information.
-pub struct PixelSerializer<I: Iterator<Item = Pixel<Gray4>>, TOrigin: Origin> {
+/// The segments are mirrored by flip, the pixels must be ordered row by row
+/// The segments are written to a staging buffer of `size` bytes
+pub struct PixelSerializer<I: Iterator<Item = Pixel<Gray4>>> {
     area: Rectangle,
     pixels: I,
     row: usize,
     max_entries: usize,
-    origin: PhantomData<TOrigin>,
+    flip: Flip,
 }
 
-impl<I: Iterator<Item = Pixel<Gray4>>, TOrigin: Origin> PixelSerializer<I, TOrigin> {
-    pub fn new(area: Rectangle, pixels: I, size: usize) -> Self {
-        PixelSerializer {
+impl<I: Iterator<Item = Pixel<Gray4>>> PixelSerializer<I> {
+    pub fn new(area: Rectangle, pixels: I, size: usize, flip: Flip) -> Result<Self, Error> {
+        // a single row must fit into the buffer
+        let bytes_per_row = get_nibbles_per_row(flip.rect(&area)) as usize * 2;
+        if size < bytes_per_row {
+            return Err(Error::BufferTooSmall);
+        }
+        Ok(PixelSerializer {
             area,
             pixels,
             row: 0,
-            // 1kByte
             max_entries: size,
-            origin: PhantomData {},
-        }
+            flip,
+        })
     }
-}
 
-impl<I: Iterator<Item = Pixel<Gray4>>, TOrigin: Origin> Iterator for PixelSerializer<I, TOrigin> {
-    type Item = (AreaImgInfo, Vec<u8>);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.row >= self.area.size.height as usize {
+    /// Serializes the next segment into the staging buffer
+    /// Returns the area and the used part of the buffer, `None` if all pixels are serialized
+    /// A buffer smaller than `size` yields no segments
+    pub fn next_into<'b>(&mut self, buffer: &'b mut [u8]) -> Option<(AreaImgInfo, &'b [u8])> {
+        if self.row >= self.area.size.height as usize || self.area.is_zero_sized() {
             return None;
         }
 
         let start_row = self.row;
+        let target = self.flip.rect(&self.area);
 
         // prepare buffer with enough capacity
-        let nibbles_per_row = get_nibbles_per_row(self.area) as usize * 2; // convert length to bytes
+        let nibbles_per_row = get_nibbles_per_row(target) as usize * 2; // convert length to bytes
         let max_rows_per_iter =
             (self.max_entries / nibbles_per_row).min(self.area.size.height as usize);
-        assert!(max_rows_per_iter > 0, "Buffer size to small for one row");
         // Make sure to not overallocate at the end of the area
         let number_of_rows_for_iter =
             max_rows_per_iter.min(self.area.size.height as usize - self.row);
 
-        let mut bytes = vec![0x00; nibbles_per_row * number_of_rows_for_iter];
+        let bytes = buffer.get_mut(..nibbles_per_row * number_of_rows_for_iter)?;
+        bytes.fill(0x00);
 
         // add all pixels to buffer
         for Pixel(point, color) in self.pixels.by_ref() {
+            // rows are stored bottom up if the y axis is mirrored
+            let row = match self.flip.y {
+                false => self.row - start_row,
+                true => number_of_rows_for_iter - 1 - (self.row - start_row),
+            };
+            let end_of_row = point.x >= self.area.top_left.x + self.area.size.width as i32 - 1;
+            let point = self.flip.point(point);
+
             // calculate the which u16 (pair of two bytes) the pixel is in
-            let (byte_pos, bit_pos) =
-                TOrigin::bit_and_byte_pos(&self.area, point, nibbles_per_row, self.row, start_row);
+            let u16_pos =
+                ((point.x - (target.top_left.x / 4 * 4)) / 2) as usize + nibbles_per_row * row;
+
+            // swap last pixel to map little endian behavior
+            let byte_pos = u16_pos ^ 0x0001;
+
+            // little endian layout
+            // [P3, P2 | P1, P0]
+            let bit_pos = (point.x % 2) * 4;
 
             bytes[byte_pos] |= (color.luma()) << bit_pos;
 
             //  end of row
-            if point.x >= self.area.top_left.x + self.area.size.width as i32 - 1 {
+            if end_of_row {
                 self.row += 1;
             }
             // abort if all rows are written to buffer
@@ -70,12 +90,30 @@ impl<I: Iterator<Item = Pixel<Gray4>>, TOrigin: Origin> Iterator for PixelSerial
             }
         }
 
+        let rows = self.row - start_row;
+        if rows == 0 {
+            // the pixels ended before the area was complete
+            return None;
+        }
+        let unused = (number_of_rows_for_iter - rows) * nibbles_per_row;
+        let (area_y, bytes) = match self.flip.y {
+            false => (
+                target.top_left.y + start_row as i32,
+                &bytes[..bytes.len() - unused],
+            ),
+            // drop the unused rows at the top if the pixels ended early
+            true => (
+                target.top_left.y + (self.area.size.height as usize - self.row) as i32,
+                &bytes[unused..],
+            ),
+        };
+
         Some((
             AreaImgInfo {
-                area_x: self.area.top_left.x as u16,
-                area_y: (self.area.top_left.y + start_row as i32) as u16,
-                area_w: self.area.size.width as u16,
-                area_h: (self.row - start_row) as u16,
+                area_x: target.top_left.x as u16,
+                area_y: area_y as u16,
+                area_w: target.size.width as u16,
+                area_h: rows as u16,
             },
             bytes,
         ))
@@ -100,9 +138,9 @@ pub fn convert_color_to_pixel_iterator<In: Iterator<Item = Gray4>, TRect: Borrow
 
 #[cfg(test)]
 mod tests {
-    use crate::origin::{OriginTopLeft, OriginTopRight};
 
     use super::*;
+    use alloc::vec::Vec;
 
     const BOUNDING_BOX_DEFAULT: Rectangle = Rectangle {
         top_left: Point { x: 0, y: 0 },
@@ -112,6 +150,22 @@ mod tests {
         },
     };
 
+    // serializes the next segment into a dirty staging buffer
+    fn next<I: Iterator<Item = Pixel<Gray4>>>(
+        s: &mut PixelSerializer<I>,
+    ) -> Option<(AreaImgInfo, Vec<u8>)> {
+        let mut buffer = vec![0xFF; s.max_entries];
+        s.next_into(&mut buffer)
+            .map(|(area, bytes)| (area, bytes.to_vec()))
+    }
+
+    // display mirrored horizontally, like a panel with the origin in the TopRight corner
+    const FLIP_X: Flip = Flip {
+        x: true,
+        y: false,
+        size: BOUNDING_BOX_DEFAULT.size,
+    };
+
     #[test]
     // single pixel in bounding box at pos 0
     fn test_pixel_0() {
@@ -122,7 +176,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -130,9 +184,11 @@ mod tests {
                 vec![Gray4::new(0xF)].into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 0,
@@ -143,7 +199,7 @@ mod tests {
                 vec![0x00, 0x0F]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -156,7 +212,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -164,9 +220,11 @@ mod tests {
                 vec![Gray4::new(0x1)].into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 1,
@@ -177,7 +235,7 @@ mod tests {
                 vec![0x00, 0x10]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
     #[test]
     // single pixel in bounding box at pos 2
@@ -189,7 +247,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -197,9 +255,11 @@ mod tests {
                 vec![Gray4::new(0x4)].into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 2,
@@ -210,7 +270,7 @@ mod tests {
                 vec![0x04, 0x00]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
     #[test]
     // single pixel in bounding box at pos 3
@@ -222,7 +282,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -230,9 +290,11 @@ mod tests {
                 vec![Gray4::new(0xC)].into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 3,
@@ -243,7 +305,7 @@ mod tests {
                 vec![0xC0, 0x00]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -256,7 +318,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -270,9 +332,11 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 4,
@@ -283,7 +347,7 @@ mod tests {
                 vec![0xDC, 0xBA]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -296,7 +360,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -304,9 +368,11 @@ mod tests {
                 vec![Gray4::new(0xC), Gray4::new(0xD), Gray4::new(0xE)].into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 3,
@@ -317,7 +383,7 @@ mod tests {
                 vec![0xC0, 0x00, 0x00, 0xED]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -330,7 +396,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -348,9 +414,11 @@ mod tests {
                 .into_iter(),
             ),
             2,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 4,
@@ -362,7 +430,7 @@ mod tests {
             ))
         );
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 4,
@@ -373,7 +441,7 @@ mod tests {
                 vec![0x43, 0x21]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -386,7 +454,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -402,9 +470,11 @@ mod tests {
                 .into_iter(),
             ),
             4,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 3,
@@ -416,7 +486,7 @@ mod tests {
             ))
         );
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 3,
@@ -427,7 +497,7 @@ mod tests {
                 vec![0x10, 0x00, 0x00, 0x32]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -440,7 +510,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -458,9 +528,11 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 4,
@@ -471,7 +543,7 @@ mod tests {
                 vec![0xDC, 0xBA, 0x43, 0x21]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -484,7 +556,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -500,9 +572,11 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 3,
@@ -513,7 +587,7 @@ mod tests {
                 vec![0xC0, 0x00, 0x00, 0xED, 0x10, 0x00, 0x00, 0x32]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -526,7 +600,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopLeft>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -542,9 +616,11 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 0,
@@ -555,7 +631,7 @@ mod tests {
                 vec![0x00, 0x32]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -568,7 +644,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -576,21 +652,22 @@ mod tests {
                 vec![Gray4::new(0xF)].into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 0,
+                    area_x: 9,
                     area_y: 0,
                     area_w: 1,
                     area_h: 1
                 },
-                // vec![0x00, 0x0F]
-                vec![0xF0, 0x00]
+                vec![0x00, 0xF0]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -603,7 +680,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -611,22 +688,24 @@ mod tests {
                 vec![Gray4::new(0x1)].into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 1,
+                    area_x: 8,
                     area_y: 1,
                     area_w: 1,
                     area_h: 1
                 },
-                //vec![0x00, 0x10]
-                vec![0x01, 0x00]
+                vec![0x00, 0x01]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
+
     #[test]
     // single pixel in bounding box at pos 2
     fn test_pixel_2_origin_top_right() {
@@ -637,7 +716,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -645,22 +724,24 @@ mod tests {
                 vec![Gray4::new(0x4)].into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 2,
+                    area_x: 7,
                     area_y: 1,
                     area_w: 1,
                     area_h: 1
                 },
-                // vec![0x04, 0x00]
-                vec![0x00, 0x40]
+                vec![0x40, 0x00]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
+
     #[test]
     // single pixel in bounding box at pos 3
     fn test_pixel_3_origin_top_right() {
@@ -671,7 +752,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -679,21 +760,22 @@ mod tests {
                 vec![Gray4::new(0xC)].into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 3,
+                    area_x: 6,
                     area_y: 1,
                     area_w: 1,
                     area_h: 1
                 },
-                // vec![0xC0, 0x00]
-                vec![0x00, 0x0C]
+                vec![0x0C, 0x00]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -706,7 +788,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -720,21 +802,22 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 4,
+                    area_x: 2,
                     area_y: 1,
                     area_w: 4,
                     area_h: 1
                 },
-                // vec![0xDC, 0xBA]
-                vec![0xAB, 0xCD]
+                vec![0xCD, 0x00, 0x00, 0xAB]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -747,7 +830,7 @@ mod tests {
                 height: 1,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -755,21 +838,22 @@ mod tests {
                 vec![Gray4::new(0xC), Gray4::new(0xD), Gray4::new(0xE)].into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 3,
+                    area_x: 4,
                     area_y: 1,
                     area_w: 3,
                     area_h: 1
                 },
-                // vec![0xC0, 0x00, 0x00, 0xED]
-                vec![0xDE, 0x00, 0x00, 0x0C]
+                vec![0x0C, 0xDE]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -782,7 +866,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -799,35 +883,35 @@ mod tests {
                 ]
                 .into_iter(),
             ),
-            2,
-        );
+            4,
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 4,
+                    area_x: 2,
                     area_y: 1,
                     area_w: 4,
                     area_h: 1
                 },
-                // vec![0xDC, 0xBA]
-                vec![0xAB, 0xCD]
+                vec![0xCD, 0x00, 0x00, 0xAB]
             ))
         );
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 4,
+                    area_x: 2,
                     area_y: 2,
                     area_w: 4,
                     area_h: 1
                 },
-                // vec![0x43, 0x21]
-                vec![0x12, 0x34]
+                vec![0x34, 0x00, 0x00, 0x12]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -840,7 +924,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -856,34 +940,22 @@ mod tests {
                 .into_iter(),
             ),
             4,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 3,
+                    area_x: 4,
                     area_y: 1,
                     area_w: 3,
-                    area_h: 1
-                },
-                // vec![0xC0, 0x00, 0x00, 0xED]
-                vec![0xDE, 0x00, 0x00, 0x0C]
-            ))
-        );
-        assert_eq!(
-            s.next(),
-            Some((
-                AreaImgInfo {
-                    area_x: 3,
-                    area_y: 2,
-                    area_w: 3,
-                    area_h: 1
+                    area_h: 2
                 },
-                // vec![0x10, 0x00, 0x00, 0x32]
-                vec![0x23, 0x00, 0x00, 0x01]
+                vec![0x0C, 0xDE, 0x01, 0x23]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -896,7 +968,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -914,21 +986,22 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 4,
+                    area_x: 2,
                     area_y: 1,
                     area_w: 4,
                     area_h: 2
                 },
-                // vec![0xDC, 0xBA, 0x43, 0x21]
-                vec![0xAB, 0xCD, 0x12, 0x34]
+                vec![0xCD, 0x00, 0x00, 0xAB, 0x34, 0x00, 0x00, 0x12]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -941,7 +1014,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -957,21 +1030,22 @@ mod tests {
                 .into_iter(),
             ),
             1024,
-        );
+            FLIP_X,
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
-                    area_x: 3,
+                    area_x: 4,
                     area_y: 1,
                     area_w: 3,
                     area_h: 2
                 },
-                //vec![0xC0, 0x00, 0x00, 0xED, 0x10, 0x00, 0x00, 0x32]
-                vec![0xDE, 0x00, 0x00, 0x0C, 0x23, 0x00, 0x00, 0x01]
+                vec![0x0C, 0xDE, 0x01, 0x23]
             ))
         );
-        assert_eq!(s.next(), None);
+        assert_eq!(next(&mut s), None);
     }
 
     #[test]
@@ -984,7 +1058,7 @@ mod tests {
                 height: 2,
             },
         };
-        let mut s = PixelSerializer::<_, OriginTopRight>::new(
+        let mut s = PixelSerializer::new(
             area.intersection(&BOUNDING_BOX_DEFAULT),
             convert_color_to_pixel_iterator(
                 area,
@@ -1000,20 +1074,181 @@ mod tests {
                 .into_iter(),
             ),
             1024,
+            FLIP_X,
+        )
+        .unwrap();
+        assert_eq!(
+            next(&mut s),
+            Some((
+                AreaImgInfo {
+                    area_x: 8,
+                    area_y: 0,
+                    area_w: 2,
+                    area_h: 1
+                },
+                vec![0x00, 0x23]
+            ))
+        );
+        assert_eq!(next(&mut s), None);
+    }
+
+    #[test]
+    // two rows of pixels, mirrored vertically
+    fn test_pixel_rows_flip_y() {
+        let area = Rectangle {
+            top_left: Point { x: 4, y: 1 },
+            size: Size {
+                width: 4,
+                height: 3,
+            },
+        };
+        let flip = Flip {
+            x: false,
+            y: true,
+            size: BOUNDING_BOX_DEFAULT.size,
+        };
+        let mut s = PixelSerializer::new(
+            area.intersection(&BOUNDING_BOX_DEFAULT),
+            convert_color_to_pixel_iterator(
+                area,
+                BOUNDING_BOX_DEFAULT,
+                vec![
+                    Gray4::new(0xA),
+                    Gray4::new(0xB),
+                    Gray4::new(0xC),
+                    Gray4::new(0xD),
+                    Gray4::new(0x1),
+                    Gray4::new(0x2),
+                    Gray4::new(0x3),
+                    Gray4::new(0x4),
+                    Gray4::new(0x5),
+                    Gray4::new(0x6),
+                    Gray4::new(0x7),
+                    Gray4::new(0x8),
+                ]
+                .into_iter(),
+            ),
+            4,
+            flip,
+        )
+        .unwrap();
+        // first two rows, stored bottom up
+        assert_eq!(
+            next(&mut s),
+            Some((
+                AreaImgInfo {
+                    area_x: 4,
+                    area_y: 7,
+                    area_w: 4,
+                    area_h: 2
+                },
+                vec![0x43, 0x21, 0xDC, 0xBA]
+            ))
+        );
+        assert_eq!(
+            next(&mut s),
+            Some((
+                AreaImgInfo {
+                    area_x: 4,
+                    area_y: 6,
+                    area_w: 4,
+                    area_h: 1
+                },
+                vec![0x87, 0x65]
+            ))
+        );
+        assert_eq!(next(&mut s), None);
+    }
+
+    #[test]
+    // pixels end after the first row, mirrored vertically
+    fn test_pixel_rows_flip_y_incomplete() {
+        let area = Rectangle {
+            top_left: Point { x: 4, y: 1 },
+            size: Size {
+                width: 4,
+                height: 2,
+            },
+        };
+        let flip = Flip {
+            x: false,
+            y: true,
+            size: BOUNDING_BOX_DEFAULT.size,
+        };
+        let mut s = PixelSerializer::new(
+            area.intersection(&BOUNDING_BOX_DEFAULT),
+            convert_color_to_pixel_iterator(
+                area,
+                BOUNDING_BOX_DEFAULT,
+                vec![
+                    Gray4::new(0xA),
+                    Gray4::new(0xB),
+                    Gray4::new(0xC),
+                    Gray4::new(0xD),
+                ]
+                .into_iter(),
+            ),
+            4,
+            flip,
+        )
+        .unwrap();
+        assert_eq!(
+            next(&mut s),
+            Some((
+                AreaImgInfo {
+                    area_x: 4,
+                    area_y: 8,
+                    area_w: 4,
+                    area_h: 1
+                },
+                vec![0xDC, 0xBA]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_pixel_buffer_too_small() {
+        let area = Rectangle::new(Point::new(0, 0), Size::new(32, 2));
+        let s = PixelSerializer::new(
+            area,
+            convert_color_to_pixel_iterator(
+                area,
+                BOUNDING_BOX_DEFAULT,
+                core::iter::repeat(Gray4::new(0xA)),
+            ),
+            4,
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
         );
+        assert_eq!(s.err(), Some(Error::BufferTooSmall));
+    }
+
+    #[test]
+    fn test_pixels_end_early() {
+        let area = Rectangle::new(Point::new(0, 0), Size::new(4, 4));
+        let mut s = PixelSerializer::new(
+            area,
+            convert_color_to_pixel_iterator(
+                area,
+                BOUNDING_BOX_DEFAULT,
+                core::iter::repeat_n(Gray4::new(0xA), 5),
+            ),
+            4,
+            Flip::none(BOUNDING_BOX_DEFAULT.size),
+        )
+        .unwrap();
         assert_eq!(
-            s.next(),
+            next(&mut s),
             Some((
                 AreaImgInfo {
                     area_x: 0,
                     area_y: 0,
-                    area_w: 2,
+                    area_w: 4,
                     area_h: 1
                 },
-                // vec![0x00, 0x32]
-                vec![0x23, 0x00]
+                vec![0xAA, 0xAA]
             ))
         );
-        assert_eq!(s.next(), None);
+        // the incomplete row is dropped
+        assert_eq!(next(&mut s), None);
     }
 }
@@ -0,0 +1,218 @@
+//! Known panels and boards using the IT8951 controller
+
+use crate::{
+    origin::Mirror, waveform_table::WaveformTable, DevInfo, Error, Rotation, WaveformMode,
+};
+
+/// Properties of a panel and the board it is shipped with
+#[derive(Debug)]
+pub struct PanelProfile {
+    /// Name of the panel
+    pub name: &'static str,
+    /// Panel width in pixels, as reported by the controller
+    pub width: u16,
+    /// Panel height in pixels, as reported by the controller
+    pub height: u16,
+    /// Mirroring of the panel, e.g. `Horizontal` for a panel with the origin in the TopRight corner
+    pub mirror: Mirror,
+    /// Default display rotation
+    pub rotation: Rotation,
+    /// The board needs `enhance_driving_capability` for a stable image
+    pub enhance_driving_capability: bool,
+    /// Typical VCOM magnitudes in millivolts printed on the panels, the exact value is panel specific
+    pub vcom_range_mv: (u16, u16),
+    /// SPI clock known to work with the board
+    pub spi_frequency_hz: u32,
+    /// Prefix of the LUT version reported by the controller, empty if unknown
+    pub lut_version: &'static str,
+    /// Mode numbers of the waveforms in the LUT shipped with the panel
+    pub waveform_table: WaveformTable,
+}
+
+impl PanelProfile {
+    fn matches_lut(&self, lut_version: &str) -> bool {
+        !self.lut_version.is_empty() && lut_version.starts_with(self.lut_version)
+    }
+
+    /// True if the LUT of the panel supports the waveform
+    pub fn supports(&self, mode: WaveformMode) -> bool {
+        self.waveform_table.supports(mode)
+    }
+}
+
+/// Waveshare 6inch e-Paper HAT, 800x600
+pub const WAVESHARE_6INCH: PanelProfile = PanelProfile {
+    name: "Waveshare 6inch",
+    width: 800,
+    height: 600,
+    mirror: Mirror::None,
+    rotation: Rotation::Rotate0,
+    enhance_driving_capability: false,
+    vcom_range_mv: (1300, 2500),
+    spi_frequency_hz: 12_000_000,
+    lut_version: "M641",
+    waveform_table: WaveformTable::M641,
+};
+
+/// Waveshare 7.8inch e-Paper HAT, 1872x1404
+pub const WAVESHARE_7_8INCH: PanelProfile = PanelProfile {
+    name: "Waveshare 7.8inch",
+    width: 1872,
+    height: 1404,
+    mirror: Mirror::None,
+    rotation: Rotation::Rotate0,
+    enhance_driving_capability: false,
+    vcom_range_mv: (1300, 2500),
+    spi_frequency_hz: 12_000_000,
+    lut_version: "M841_TFA2812",
+    waveform_table: WaveformTable::STANDARD,
+};
+
+/// Waveshare 9.7inch e-Paper HAT, 1200x825
+pub const WAVESHARE_9_7INCH: PanelProfile = PanelProfile {
+    name: "Waveshare 9.7inch",
+    width: 1200,
+    height: 825,
+    mirror: Mirror::None,
+    rotation: Rotation::Rotate0,
+    enhance_driving_capability: true,
+    vcom_range_mv: (1300, 2500),
+    spi_frequency_hz: 12_000_000,
+    lut_version: "M841",
+    waveform_table: WaveformTable::STANDARD,
+};
+
+/// Waveshare 10.3inch e-Paper HAT, 1872x1404
+pub const WAVESHARE_10_3INCH: PanelProfile = PanelProfile {
+    name: "Waveshare 10.3inch",
+    width: 1872,
+    height: 1404,
+    mirror: Mirror::Horizontal,
+    rotation: Rotation::Rotate0,
+    enhance_driving_capability: false,
+    vcom_range_mv: (1300, 2500),
+    spi_frequency_hz: 12_000_000,
+    lut_version: "M841_TFA5210",
+    waveform_table: WaveformTable::STANDARD,
+};
+
+/// Waveshare 13.3inch e-Paper HAT, 1600x1200
+pub const WAVESHARE_13_3INCH: PanelProfile = PanelProfile {
+    name: "Waveshare 13.3inch",
+    width: 1600,
+    height: 1200,
+    mirror: Mirror::None,
+    rotation: Rotation::Rotate0,
+    enhance_driving_capability: false,
+    vcom_range_mv: (1300, 2500),
+    spi_frequency_hz: 12_000_000,
+    lut_version: "",
+    waveform_table: WaveformTable::STANDARD,
+};
+
+/// M5Stack M5Paper, 960x540
+pub const M5PAPER: PanelProfile = PanelProfile {
+    name: "M5Paper",
+    width: 960,
+    height: 540,
+    mirror: Mirror::None,
+    rotation: Rotation::Rotate0,
+    enhance_driving_capability: false,
+    vcom_range_mv: (2200, 2400),
+    spi_frequency_hz: 10_000_000,
+    lut_version: "",
+    waveform_table: WaveformTable::STANDARD,
+};
+
+/// All known profiles
+pub const PROFILES: &[&PanelProfile] = &[
+    &WAVESHARE_6INCH,
+    &WAVESHARE_7_8INCH,
+    &WAVESHARE_9_7INCH,
+    &WAVESHARE_10_3INCH,
+    &WAVESHARE_13_3INCH,
+    &M5PAPER,
+];
+
+/// Finds the profile of a connected panel from the reported size and LUT version
+/// Panels of the same size are told apart by the longest matching LUT version prefix
+/// Returns `None` for an unknown panel size and fails with `Error::AmbiguousPanel`
+/// if several panels have the size but none matches the LUT version
+pub fn detect(dev_info: &DevInfo) -> Result<Option<&'static PanelProfile>, Error> {
+    let mut candidates = PROFILES
+        .iter()
+        .copied()
+        .filter(|p| (p.width, p.height) == (dev_info.panel_width, dev_info.panel_height));
+
+    let Some(first) = candidates.next() else {
+        return Ok(None);
+    };
+    let mut best = first.matches_lut(&dev_info.lut_version).then_some(first);
+    let mut ambiguous = false;
+    for profile in candidates {
+        ambiguous = true;
+        if profile.matches_lut(&dev_info.lut_version)
+            && best.is_none_or(|b| profile.lut_version.len() > b.lut_version.len())
+        {
+            best = Some(profile);
+        }
+    }
+
+    match best {
+        Some(profile) => Ok(Some(profile)),
+        None if !ambiguous => Ok(Some(first)),
+        None => Err(Error::AmbiguousPanel),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        for profile in PROFILES {
+            assert!(profile.width > 0 && profile.height > 0);
+            assert!(profile.vcom_range_mv.0 <= profile.vcom_range_mv.1);
+            assert!(profile.supports(WaveformMode::Init));
+            assert!(profile.supports(WaveformMode::GrayscaleClearing16));
+        }
+        assert!(!WAVESHARE_6INCH.supports(WaveformMode::DU4));
+        assert_eq!(WAVESHARE_10_3INCH.mirror, Mirror::Horizontal);
+    }
+
+    fn dev_info(width: u16, height: u16, lut_version: &str) -> DevInfo {
+        DevInfo {
+            panel_width: width,
+            panel_height: height,
+            memory_address: 0x001236E0,
+            firmware_version: alloc::string::String::from("SWv_0.2.1.T"),
+            lut_version: alloc::string::String::from(lut_version),
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let detected =
+            |width, height, lut| detect(&dev_info(width, height, lut)).map(|p| p.map(|p| p.name));
+
+        assert_eq!(
+            detected(1872, 1404, "M841_TFA5210"),
+            Ok(Some(WAVESHARE_10_3INCH.name))
+        );
+        assert_eq!(
+            detected(1872, 1404, "M841_TFA2812"),
+            Ok(Some(WAVESHARE_7_8INCH.name))
+        );
+        assert_eq!(
+            detected(1200, 825, "M841_TFA5210"),
+            Ok(Some(WAVESHARE_9_7INCH.name))
+        );
+        assert_eq!(detected(800, 600, "M641"), Ok(Some(WAVESHARE_6INCH.name)));
+        assert_eq!(detected(960, 540, ""), Ok(Some(M5PAPER.name)));
+        // same size, unknown LUT
+        assert_eq!(detected(1872, 1404, "M841"), Err(Error::AmbiguousPanel));
+        // unknown size
+        assert_eq!(detected(1024, 758, "M641"), Ok(None));
+    }
+}
@@ -0,0 +1,157 @@
+//! Read back the content of the image buffer
+
+use alloc::vec::Vec;
+use embedded_graphics_core::{
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+use crate::{
+    image_buffer::ImageBuffer, interface, origin::Origin, AreaImgInfo, Error, Run, IT8951,
+};
+
+impl<IT8951Interface: interface::IT8951Interface, TOrigin: Origin>
+    IT8951<IT8951Interface, TOrigin, Run>
+{
+    /// Reads the pixels of a area from the default image buffer
+    /// The area uses display coordinates like all drawing operations and is clipped to the display
+    /// Pixels are returned row by row
+    pub fn read_area(&mut self, area: &Rectangle) -> Result<Vec<Gray4>, Error> {
+        let addr = self.image_buffer()?.addr;
+        self.read_area_at(addr, area)
+    }
+
+    /// Reads the pixels of a area from the panel sized 8bpp image buffer at addr
+    pub(crate) fn read_area_at(
+        &mut self,
+        addr: u32,
+        area: &Rectangle,
+    ) -> Result<Vec<Gray4>, Error> {
+        let area = area.intersection(&self.bounding_box());
+        let mut pixels = vec![Gray4::BLACK; area.size.width as usize * area.size.height as usize];
+        if area.is_zero_sized() {
+            return Ok(pixels);
+        }
+
+        let physical = self.panel_area_info(&AreaImgInfo::try_from(&area)?)?;
+
+        let orientation = self.panel_orientation();
+        let panel = self.panel_size()?;
+        let mut x = physical.area_x;
+        let mut y = physical.area_y;
+        self.read_physical_area(addr, &physical, |byte| {
+            let point =
+                orientation.point_to_display(Point::new(x as i32, y as i32), panel) - area.top_left;
+            pixels[point.y as usize * area.size.width as usize + point.x as usize] =
+                Gray4::new(byte >> 4);
+
+            x += 1;
+            if x >= physical.area_x + physical.area_w {
+                x = physical.area_x;
+                y += 1;
+            }
+        })?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Read back area {}", physical);
+
+        Ok(pixels)
+    }
+
+    /// Streams the complete default image buffer as 8bit grayscale values, row by row
+    /// The image is not rotated and uses the panel orientation
+    /// Each chunk is at most `Config::max_buffer_size` bytes large
+    pub fn screenshot_with<F>(&mut self, mut sink: F) -> Result<(), Error>
+    where
+        F: FnMut(&[u8]) -> Result<(), Error>,
+    {
+        let frame = self.image_buffer()?;
+        let chunk_size = self.config.max_buffer_size & !1;
+
+        self.with_staging(|it8951, data| {
+            for row in 0..frame.height as u32 {
+                let row_addr = frame.addr + row * frame.bytes_per_row();
+                for start in (0..frame.width as usize).step_by(chunk_size) {
+                    let len = chunk_size.min(frame.width as usize - start);
+                    it8951.memory_burst_read(
+                        row_addr + start as u32,
+                        &mut data[..len.next_multiple_of(2)],
+                    )?;
+                    sink(&data[..len])?;
+                }
+            }
+            Ok(())
+        })?;
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("Streamed screenshot");
+
+        Ok(())
+    }
+
+    /// Copies the complete default image buffer as 8bit grayscale values into buf
+    /// The image is not rotated and uses the panel orientation
+    pub fn screenshot(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let frame = self.image_buffer()?;
+        if buf.len() < frame.width as usize * frame.height as usize {
+            return Err(Error::BufferTooSmall);
+        }
+
+        let mut pos = 0;
+        self.screenshot_with(|chunk| {
+            buf[pos..pos + chunk.len()].copy_from_slice(chunk);
+            pos += chunk.len();
+            Ok(())
+        })
+    }
+
+    /// Writes the complete default image buffer as binary PGM image
+    /// The image is not rotated and uses the panel orientation
+    #[cfg(feature = "std")]
+    pub fn screenshot_pgm<W: std::io::Write>(&mut self, mut writer: W) -> Result<(), Error> {
+        let frame = self.image_buffer()?;
+        write!(writer, "P5\n{} {}\n255\n", frame.width, frame.height)
+            .map_err(|e| Error::Io(e.kind()))?;
+        self.screenshot_with(|chunk| writer.write_all(chunk).map_err(|e| Error::Io(e.kind())))?;
+        writer.flush().map_err(|e| Error::Io(e.kind()))
+    }
+
+    /// Reads a area of the panel sized 8bpp image buffer at addr in panel coordinates
+    /// Every pixel value is passed to f, row by row
+    fn read_physical_area<F>(
+        &mut self,
+        addr: u32,
+        area: &AreaImgInfo,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(u8),
+    {
+        let frame = ImageBuffer {
+            addr,
+            ..self.image_buffer()?
+        };
+        let chunk_size = self.config.max_buffer_size & !1;
+
+        self.with_staging(|it8951, data| {
+            for row in area.area_y as u32..(area.area_y + area.area_h) as u32 {
+                let row_addr = frame.addr + row * frame.bytes_per_row();
+                let mut x = area.area_x as u32;
+                let end = (area.area_x + area.area_w) as u32;
+                while x < end {
+                    // bursts start at a u16 boundary
+                    let aligned_x = x & !1;
+                    let len = (chunk_size as u32).min((end - aligned_x).next_multiple_of(2));
+                    it8951.memory_burst_read(row_addr + aligned_x, &mut data[..len as usize])?;
+
+                    let skip = (x - aligned_x) as usize;
+                    let count = (len as usize - skip).min((end - x) as usize);
+                    data[skip..skip + count].iter().for_each(|byte| f(*byte));
+                    x += count as u32;
+                }
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,47 @@
+//! Non-blocking display refreshes
+
+use crate::{
+    interface::IT8951Interface, origin::Origin, register, AreaImgInfo, Error, Run, WaveformMode,
+    IT8951,
+};
+
+/// Handle of a running display refresh
+/// The driver is borrowed until the handle is dropped or the refresh is awaited
+pub struct RefreshHandle<'a, Interface, TOrigin: Origin> {
+    driver: &'a mut IT8951<Interface, TOrigin, Run>,
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> RefreshHandle<'_, Interface, TOrigin> {
+    /// Polls the display engine once, true if the refresh finished
+    pub fn is_done(&mut self) -> Result<bool, Error> {
+        self.driver.is_display_ready()
+    }
+
+    /// Blocks until the refresh finished or the display engine timeout elapsed
+    pub fn wait(self) -> Result<(), Error> {
+        self.driver.wait_for_display_ready()
+    }
+}
+
+impl<Interface: IT8951Interface, TOrigin: Origin> IT8951<Interface, TOrigin, Run> {
+    /// Polls the display engine once, true if no refresh is running
+    pub fn is_display_ready(&mut self) -> Result<bool, Error> {
+        let ready = self.read_register(register::LUTAFSR)? == 0;
+        if ready {
+            self.in_flight.clear();
+        }
+        Ok(ready)
+    }
+
+    /// Starts a refresh of a specific area of the display with the frame buffer content
+    /// Waits for a previous refresh, but returns as soon as the refresh is issued
+    /// The returned handle reports when the refresh finished
+    pub fn start_display_area(
+        &mut self,
+        area_info: &AreaImgInfo,
+        mode: WaveformMode,
+    ) -> Result<RefreshHandle<'_, Interface, TOrigin>, Error> {
+        self.display_area(area_info, mode)?;
+        Ok(RefreshHandle { driver: self })
+    }
+}
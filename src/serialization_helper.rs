@@ -10,6 +10,23 @@ pub fn get_nibbles_per_row(area: Rectangle) -> u32 {
     (area.size.width + alignment_pixels).div_ceil(PIXEL_PER_WORD)
 }
 
+/// Reads the value of pixel x from a packed row of pixels
+/// Pixels are stored in memory order, starting at the least significant bits of each byte
+pub fn get_packed_pixel(row: &[u8], x: usize, bpp: u8) -> u8 {
+    let bit_pos = x * bpp as usize;
+    let mask = ((1u16 << bpp) - 1) as u8;
+    (row[bit_pos / 8] >> (bit_pos % 8)) & mask
+}
+
+/// Sets the value of pixel x in a packed row of pixels
+/// Pixels are stored in memory order, starting at the least significant bits of each byte
+pub fn set_packed_pixel(row: &mut [u8], x: usize, bpp: u8, value: u8) {
+    let bit_pos = x * bpp as usize;
+    let mask = (((1u16 << bpp) - 1) as u8) << (bit_pos % 8);
+    let byte = &mut row[bit_pos / 8];
+    *byte = (*byte & !mask) | ((value << (bit_pos % 8)) & mask);
+}
+
 #[cfg(test)]
 mod tests {
     use embedded_graphics_core::geometry::{Point, Size};
@@ -56,4 +73,25 @@ mod tests {
         off_by_three_6: (3, 6, 3),
         off_by_four_6: (4, 1, 1),
     }
+
+    #[test]
+    fn packed_pixel_4bpp() {
+        let mut row = [0x21, 0x43];
+        assert_eq!(get_packed_pixel(&row, 0, 4), 0x1);
+        assert_eq!(get_packed_pixel(&row, 3, 4), 0x4);
+        set_packed_pixel(&mut row, 2, 4, 0xF);
+        assert_eq!(row, [0x21, 0x4F]);
+    }
+
+    #[test]
+    fn packed_pixel_1bpp_and_8bpp() {
+        let mut row = [0b1000_0001, 0x00];
+        assert_eq!(get_packed_pixel(&row, 7, 1), 1);
+        assert_eq!(get_packed_pixel(&row, 6, 1), 0);
+        set_packed_pixel(&mut row, 8, 1, 1);
+        assert_eq!(row, [0b1000_0001, 0b0000_0001]);
+
+        set_packed_pixel(&mut row, 1, 8, 0xAB);
+        assert_eq!(get_packed_pixel(&row, 1, 8), 0xAB);
+    }
 }
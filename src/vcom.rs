@@ -0,0 +1,75 @@
+//! Panel VCOM voltage
+
+use crate::Error;
+
+/// VCOM voltage of a panel
+/// Panels are labeled with a negative voltage like "VCOM -1.53V",
+/// the controller stores the magnitude in millivolts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Vcom(u16);
+
+impl Vcom {
+    /// Smallest VCOM magnitude accepted by the controller in millivolts
+    pub const MIN_MILLIVOLTS: u16 = 200;
+    /// Largest VCOM magnitude accepted by the controller in millivolts
+    pub const MAX_MILLIVOLTS: u16 = 5000;
+
+    /// VCOM from a value in millivolts, e.g. -1530 or 1530 for a panel labeled "VCOM -1.53V"
+    pub fn from_millivolts(millivolts: i32) -> Result<Self, Error> {
+        let magnitude = millivolts.unsigned_abs();
+        if !(Self::MIN_MILLIVOLTS as u32..=Self::MAX_MILLIVOLTS as u32).contains(&magnitude) {
+            return Err(Error::InvalidVcom);
+        }
+        Ok(Vcom(magnitude as u16))
+    }
+
+    /// VCOM from a value in volts, e.g. -1.53 or 1.53 for a panel labeled "VCOM -1.53V"
+    /// The value is rounded to millivolts
+    pub fn from_volts(volts: f32) -> Result<Self, Error> {
+        if !volts.is_finite() || volts.abs() > i32::MAX as f32 / 1000.0 {
+            return Err(Error::InvalidVcom);
+        }
+        let millivolts = volts.abs() * 1000.0 + 0.5;
+        Self::from_millivolts(millivolts as i32)
+    }
+
+    /// VCOM as reported by the controller, which is not validated
+    pub(crate) fn from_raw(raw: u16) -> Self {
+        Vcom(raw)
+    }
+
+    /// Magnitude in millivolts, as stored by the controller
+    pub fn millivolts(&self) -> u16 {
+        self.0
+    }
+
+    /// Voltage in volts, negative like the panel label
+    pub fn volts(&self) -> f32 {
+        -(self.0 as f32) / 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vcom_from_millivolts() {
+        assert_eq!(Vcom::from_millivolts(-1530).unwrap().millivolts(), 1530);
+        assert_eq!(Vcom::from_millivolts(1530).unwrap().millivolts(), 1530);
+        assert_eq!(Vcom::from_millivolts(199), Err(Error::InvalidVcom));
+        assert_eq!(Vcom::from_millivolts(-5001), Err(Error::InvalidVcom));
+        assert_eq!(Vcom::from_millivolts(i32::MIN), Err(Error::InvalidVcom));
+    }
+
+    #[test]
+    fn test_vcom_from_volts() {
+        assert_eq!(Vcom::from_volts(-1.53).unwrap().millivolts(), 1530);
+        assert_eq!(Vcom::from_volts(2.3).unwrap().millivolts(), 2300);
+        assert_eq!(Vcom::from_volts(-1.53).unwrap().volts(), -1.53);
+        assert_eq!(Vcom::from_volts(0.1), Err(Error::InvalidVcom));
+        assert_eq!(Vcom::from_volts(f32::NAN), Err(Error::InvalidVcom));
+        assert_eq!(Vcom::from_volts(-1e12), Err(Error::InvalidVcom));
+    }
+}
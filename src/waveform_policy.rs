@@ -0,0 +1,114 @@
+//! Automatic selection of the waveform for display updates
+
+use crate::WaveformMode;
+
+/// Waveform for a display update
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UpdateMode {
+    /// The configured `WaveformPolicy` selects the waveform from the drawn content
+    Auto,
+    /// Always use the given waveform
+    Mode(WaveformMode),
+}
+
+impl From<WaveformMode> for UpdateMode {
+    fn from(mode: WaveformMode) -> Self {
+        UpdateMode::Mode(mode)
+    }
+}
+
+/// Summary of the content drawn into a area since its last refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AreaContent {
+    /// Bit n is set, if the gray level n was drawn
+    pub levels: u16,
+}
+
+impl AreaContent {
+    /// Content with every gray level, used if the drawn pixels are unknown
+    pub const UNKNOWN: AreaContent = AreaContent { levels: 0xFFFF };
+
+    /// True if no pixel was changed
+    pub fn is_unchanged(&self) -> bool {
+        self.levels == 0
+    }
+
+    /// True if only black and white pixels were drawn
+    pub fn is_black_white(&self) -> bool {
+        self.levels & !(1 << 0 | 1 << 15) == 0
+    }
+
+    /// True if only the gray levels supported by DU4 were drawn: 0, 5, 10 and 15
+    pub fn is_gray4_levels(&self) -> bool {
+        self.levels & !(1 << 0 | 1 << 5 | 1 << 10 | 1 << 15) == 0
+    }
+}
+
+/// Selects the waveform for a display update
+/// Policies must be `Send`, so that the driver can be moved into another task or thread
+pub trait WaveformPolicy: Send {
+    /// Returns the waveform to refresh a area with the given content
+    /// `None` skips the refresh
+    fn select(&self, content: &AreaContent) -> Option<WaveformMode>;
+}
+
+/// Default policy, selects the fastest waveform rendering the content correctly
+/// - unchanged areas are skipped
+/// - black and white content uses DU
+/// - content with 4 gray levels uses DU4
+/// - all other content uses GC16
+#[derive(Debug, Default)]
+pub struct DefaultWaveformPolicy;
+
+impl WaveformPolicy for DefaultWaveformPolicy {
+    fn select(&self, content: &AreaContent) -> Option<WaveformMode> {
+        if content.is_unchanged() {
+            None
+        } else if content.is_black_white() {
+            Some(WaveformMode::DirectUpdate)
+        } else if content.is_gray4_levels() {
+            Some(WaveformMode::DU4)
+        } else {
+            Some(WaveformMode::GrayscaleClearing16)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[u8]) -> AreaContent {
+        AreaContent {
+            levels: levels.iter().fold(0, |acc, l| acc | 1 << l),
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = DefaultWaveformPolicy;
+        assert!(policy.select(&levels(&[])).is_none());
+        assert!(matches!(
+            policy.select(&levels(&[0, 15])),
+            Some(WaveformMode::DirectUpdate)
+        ));
+        assert!(matches!(
+            policy.select(&levels(&[15])),
+            Some(WaveformMode::DirectUpdate)
+        ));
+        assert!(matches!(
+            policy.select(&levels(&[0, 5, 10])),
+            Some(WaveformMode::DU4)
+        ));
+        assert!(matches!(
+            policy.select(&levels(&[0, 7, 15])),
+            Some(WaveformMode::GrayscaleClearing16)
+        ));
+        assert!(matches!(
+            policy.select(&AreaContent::UNKNOWN),
+            Some(WaveformMode::GrayscaleClearing16)
+        ));
+    }
+}
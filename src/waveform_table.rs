@@ -0,0 +1,117 @@
+//! Mode numbers of the waveforms in the LUT loaded on the controller
+
+use crate::{Error, WaveformMode};
+
+/// Maps the waveforms to the mode numbers of the LUT loaded on the controller
+/// The numbering depends on the waveform file, some LUTs lack waveforms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WaveformTable {
+    numbers: [Option<u16>; 8],
+}
+
+/// Tables of LUTs which differ from the standard numbering, by LUT version prefix
+const KNOWN_LUTS: &[(&str, WaveformTable)] = &[("M641", WaveformTable::M641)];
+
+impl WaveformTable {
+    /// Table without any waveform
+    pub const EMPTY: WaveformTable = WaveformTable { numbers: [None; 8] };
+
+    /// Standard numbering of LUTs with all 8 waveforms, e.g. M841
+    pub const STANDARD: WaveformTable = WaveformTable::EMPTY
+        .with(WaveformMode::Init, 0)
+        .with(WaveformMode::DirectUpdate, 1)
+        .with(WaveformMode::GrayscaleClearing16, 2)
+        .with(WaveformMode::GL16, 3)
+        .with(WaveformMode::GLR16, 4)
+        .with(WaveformMode::GLD16, 5)
+        .with(WaveformMode::A2, 6)
+        .with(WaveformMode::DU4, 7);
+
+    /// M641 LUT of the 6inch panels, without GLR16, GLD16 and DU4 and with A2 as mode 4
+    pub const M641: WaveformTable = WaveformTable::EMPTY
+        .with(WaveformMode::Init, 0)
+        .with(WaveformMode::DirectUpdate, 1)
+        .with(WaveformMode::GrayscaleClearing16, 2)
+        .with(WaveformMode::GL16, 3)
+        .with(WaveformMode::A2, 4);
+
+    /// Sets the mode number of a waveform
+    pub const fn with(mut self, mode: WaveformMode, number: u16) -> Self {
+        self.numbers[mode as usize] = Some(number);
+        self
+    }
+
+    /// Removes a waveform missing in the LUT
+    pub const fn without(mut self, mode: WaveformMode) -> Self {
+        self.numbers[mode as usize] = None;
+        self
+    }
+
+    /// Table for the LUT version reported by the controller
+    /// Unknown LUT versions use the standard numbering
+    pub fn from_lut_version(lut_version: &str) -> Self {
+        KNOWN_LUTS
+            .iter()
+            .find(|(prefix, _)| lut_version.starts_with(prefix))
+            .map_or(WaveformTable::STANDARD, |(_, table)| *table)
+    }
+
+    /// True if the LUT contains the waveform
+    pub fn supports(&self, mode: WaveformMode) -> bool {
+        self.numbers[mode as usize].is_some()
+    }
+
+    /// Mode number of the waveform, fails with `Error::UnsupportedWaveform` if the LUT lacks it
+    pub fn number(&self, mode: WaveformMode) -> Result<u16, Error> {
+        self.numbers[mode as usize].ok_or(Error::UnsupportedWaveform)
+    }
+}
+
+impl Default for WaveformTable {
+    fn default() -> Self {
+        WaveformTable::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_table() {
+        let table = WaveformTable::default();
+        assert_eq!(table.number(WaveformMode::Init), Ok(0));
+        assert_eq!(table.number(WaveformMode::GLR16), Ok(4));
+        assert_eq!(table.number(WaveformMode::A2), Ok(6));
+        assert_eq!(table.number(WaveformMode::DU4), Ok(7));
+    }
+
+    #[test]
+    fn test_from_lut_version() {
+        let table = WaveformTable::from_lut_version("M641");
+        assert_eq!(table, WaveformTable::M641);
+        assert_eq!(table.number(WaveformMode::A2), Ok(4));
+        assert_eq!(
+            table.number(WaveformMode::DU4),
+            Err(Error::UnsupportedWaveform)
+        );
+        assert!(!table.supports(WaveformMode::GLR16));
+        assert!(!table.supports(WaveformMode::GLD16));
+
+        assert_eq!(
+            WaveformTable::from_lut_version("M841_TFA5210"),
+            WaveformTable::STANDARD
+        );
+        assert_eq!(WaveformTable::from_lut_version(""), WaveformTable::STANDARD);
+    }
+
+    #[test]
+    fn test_custom_table() {
+        let table = WaveformTable::STANDARD
+            .with(WaveformMode::DU4, 5)
+            .without(WaveformMode::GLD16);
+        assert_eq!(table.number(WaveformMode::DU4), Ok(5));
+        assert!(!table.supports(WaveformMode::GLD16));
+    }
+}